@@ -0,0 +1,77 @@
+use super::*;
+
+/// Error-handling statements for the body of a function whose last result is `error`.
+///
+/// e.g. for results `(int, error)`, `wrap("err", "open config")` is
+/// ```go
+/// if err != nil {
+///     return 0, fmt.Errorf("open config: %w", err)
+/// }
+/// ```
+/// Expressions record the `fmt`/`errors` imports they use.
+#[derive(Clone)]
+pub struct ErrFlow {
+    /// Zero values of the results before the error.
+    pub zeros: Vec<Expr>,
+}
+
+impl ErrFlow {
+    pub fn new(sig: &Signature) -> Result<Self, Error> {
+        match sig.results.split_last() {
+            Some((last, rest)) if last.ty.0 == "error" => {
+                Ok(Self { zeros: rest.iter().map(|p| p.ty.zero()).collect() })
+            }
+            _ => Err(Error::NoErrorResult),
+        }
+    }
+
+    /// e.g. `return 0, "", err`
+    pub fn ret(&self, err: Expr) -> Stmt {
+        Stmt::ret(self.zeros.iter().cloned().chain([err]))
+    }
+
+    /// e.g. `return 0, errors.New("msg")`
+    pub fn fail(&self, msg: impl ToString) -> Stmt {
+        self.ret(Expr::raw("errors.New").with_import("errors").call([Expr::str(msg)]))
+    }
+
+    /// e.g. `if err != nil { return 0, err }`
+    pub fn check(&self, err: impl Display) -> Stmt {
+        let err = Expr::raw(err);
+        Self::if_err(err.clone(), self.ret(err))
+    }
+
+    /// e.g. `if err != nil { return 0, fmt.Errorf("msg: %w", err) }`, a `%` in `msg` is escaped.
+    pub fn wrap(&self, err: impl Display, msg: impl Display) -> Stmt {
+        let err = Expr::raw(err);
+        let msg = msg.to_string().replace('%', "%%");
+        let errorf = Expr::raw("fmt.Errorf").with_import("fmt");
+        let wrapped = errorf.call([format!("{msg}: %w").into(), err.clone()]);
+        Self::if_err(err, self.ret(wrapped))
+    }
+
+    fn if_err(err: Expr, ret: Stmt) -> Stmt {
+        Stmt::if_([(err.binop("!=", Expr::raw("nil")), Block::new().push(ret))], Block::new())
+    }
+
+    /// e.g. `if errors.Is(err, io.EOF) { ... }`
+    pub fn is(err: Expr, target: Expr, body: Block) -> Stmt {
+        let cond = Expr::raw("errors.Is").with_import("errors").call([err, target]);
+        Stmt::if_([(cond, body)], Block::new())
+    }
+
+    /// e.g. `var pathErr *fs.PathError` and `if errors.As(err, &pathErr) { ... }`, to push in
+    /// order.
+    pub fn as_(err: Expr, bind: impl Display, ty: Type, body: Block) -> [Stmt; 2] {
+        let errors_as = Expr::raw("errors.As").with_import("errors");
+        let cond = errors_as.call([err, Expr::raw(format!("&{bind}"))]);
+        [Stmt::uninit_var(bind, ty), Stmt::if_([(cond, body)], Block::new())]
+    }
+}
+
+impl Decl {
+    /// A sentinel error. e.g. `var ErrNotFound = errors.New("not found")`
+    pub fn sentinel(name: impl Display, msg: impl ToString) -> Self {
+        Self::variable(name, Expr::raw("errors.New").with_import("errors").call([Expr::str(msg)]))
+    }
+}
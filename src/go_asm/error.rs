@@ -0,0 +1,73 @@
+use super::*;
+
+/// Errors reported while building Go code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A struct tag key is empty or contains a space, quote, backquote, colon or control character.
+    InvalidTagKey(String),
+    /// A struct tag value that can't be put in a raw string literal.
+    InvalidTagValue(String),
+    /// The same key appears twice in a struct tag.
+    DuplicateTagKey(String),
+    /// A type declares the same method twice.
+    DuplicateMethod(String),
+    /// A type lacks a method required by an interface.
+    MissingMethod(String),
+    /// A method exists but its signature differs from the interface.
+    MethodMismatch(String),
+    /// A method required by an interface is only declared on the pointer receiver.
+    PointerReceiver(String),
+    /// The init statement of a `for` clause isn't a simple statement.
+    InvalidForInit(String),
+    /// The post statement of a `for` clause isn't a simple statement or declares a variable.
+    InvalidForPost(String),
+    /// The init statement of a `switch` isn't a simple statement.
+    InvalidSwitchInit(String),
+    /// `fallthrough` in the final clause of a switch.
+    FallthroughInLastCase,
+    /// `fallthrough` isn't permitted in a type switch.
+    FallthroughInTypeSwitch,
+    /// A `case` clause of a switch without values.
+    EmptyCase,
+    /// The last result of a signature isn't `error`.
+    NoErrorResult,
+    /// A case of a table-driven test doesn't have one value per field.
+    TestCaseValues(String),
+    /// A field of a table-driven test is named `name`, like the implicit field of the case name.
+    TestCaseName,
+    /// A module file path that is absolute, leaves the module directory or isn't a `.go` file.
+    InvalidPath(String),
+    /// Files of one module directory declare different packages.
+    MixedPackages(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidTagKey(key) => write!(f, "invalid struct tag key `{key}`"),
+            Self::InvalidTagValue(value) => write!(f, "invalid struct tag value `{value}`"),
+            Self::DuplicateTagKey(key) => write!(f, "duplicate struct tag key `{key}`"),
+            Self::DuplicateMethod(name) => write!(f, "method `{name}` is already declared"),
+            Self::MissingMethod(name) => write!(f, "missing method `{name}`"),
+            Self::MethodMismatch(name) => write!(f, "method `{name}` has the wrong signature"),
+            Self::PointerReceiver(name) => write!(f, "method `{name}` has a pointer receiver"),
+            Self::InvalidForInit(stmt) => write!(f, "invalid `for` init statement `{stmt}`"),
+            Self::InvalidForPost(stmt) => write!(f, "invalid `for` post statement `{stmt}`"),
+            Self::InvalidSwitchInit(stmt) => write!(f, "invalid `switch` init statement `{stmt}`"),
+            Self::FallthroughInLastCase => write!(f, "cannot fallthrough final case in switch"),
+            Self::FallthroughInTypeSwitch => write!(f, "cannot fallthrough in type switch"),
+            Self::EmptyCase => write!(f, "switch case without values"),
+            Self::NoErrorResult => write!(f, "the last result isn't `error`"),
+            Self::TestCaseValues(name) => {
+                write!(f, "test case `{name}` doesn't have one value per field")
+            }
+            Self::TestCaseName => write!(f, "test case field `name` is reserved for the case name"),
+            Self::InvalidPath(path) => write!(f, "invalid module file path `{path}`"),
+            Self::MixedPackages(dir) => {
+                write!(f, "files in directory `{dir}` declare different packages")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,106 +1,141 @@
 use super::*;
 use std::ops::*;
 
+/// An expression. Its [`Meta`] is merged into the statement or declaration that embeds it.
 #[derive(Clone)]
-pub struct Expr(pub String);
+pub struct Expr(pub String, pub Meta);
 
 impl Expr {
     pub fn raw(raw: impl ToString) -> Self {
-        Self(raw.to_string())
+        Self(raw.to_string(), Meta::new())
     }
 
+    /// A string literal, with quotes, backslashes and control characters escaped.
     pub fn str(str: impl ToString) -> Self {
-        Self(format!("\"{}\"", str.to_string().replace("\"", "\\\"")))
+        let mut res = String::from('"');
+        for c in str.to_string().chars() {
+            match c {
+                '"' => res.push_str("\\\""),
+                '\\' => res.push_str("\\\\"),
+                '\n' => res.push_str("\\n"),
+                '\r' => res.push_str("\\r"),
+                '\t' => res.push_str("\\t"),
+                c if c.is_control() => write!(res, "\\u{:04x}", c as u32).unwrap(),
+                c => res.push(c),
+            }
+        }
+        res.push('"');
+        Self::raw(res)
+    }
+
+    /// Record that the expression refers to package `lib`.
+    pub fn with_import(mut self, lib: impl Display) -> Self {
+        self.1.imports.insert(lib.to_string());
+        self
+    }
+
+    /// Record that the expression refers to the package in `dir` of its [`Module`], see
+    /// [`Meta::module_import`].
+    pub fn with_module_import(mut self, dir: impl Display) -> Self {
+        self.1 = self.1.module_import(dir);
+        self
+    }
+
+    /// Merge the meta of nested nodes.
+    fn with_meta(mut self, metas: impl IntoIterator<Item = Meta>) -> Self {
+        for meta in metas {
+            self.1.merge(meta);
+        }
+        self
+    }
+
+    /// `exprs` separated by `sep`, with the meta of all of them. e.g. `a, b`
+    pub(crate) fn join(exprs: impl IntoIterator<Item = Expr>, sep: &str) -> Self {
+        let (exprs, metas): (Vec<_>, Vec<_>) = exprs.into_iter().map(|e| (e.0, e.1)).unzip();
+        Self::raw(exprs.join(sep)).with_meta(metas)
     }
 
     pub fn attr(self, attr: impl Display) -> Self {
-        Self(format!("{self}.{attr}"))
+        Self(format!("{self:#}.{attr}"), self.1)
     }
 
     /// Create a type assertion expression. e.g. `x.(int)`
     pub fn type_as(self, ty: Type) -> Self {
-        Self(format!("{self}.({ty})"))
+        Self(format!("{self:#}.({ty})"), self.1)
     }
 
     /// Create a type switch expression. e.g. `x.(type)`
     pub fn type_sw(self) -> Self {
-        Self(format!("{self}.(type)"))
+        Self(format!("{self:#}.(type)"), self.1)
+    }
+
+    /// Receive from a channel. e.g. `<-ch`
+    pub fn recv(self) -> Self {
+        Self(format!("<-{self:#}"), self.1)
     }
 
     pub fn range(self) -> Self {
-        Self(format!("range {self}"))
+        Self(format!("range {self:#}"), self.1)
     }
 
     pub fn binop(self, op: &str, rhs: Expr) -> Self {
-        Self(format!("({self} {op} {rhs})"))
+        Self(format!("({self:#} {op} {rhs:#})"), self.1).with_meta([rhs.1])
     }
 
     pub fn index(self, index: Expr) -> Self {
-        Self(format!("{self}[{index}]"))
+        Self(format!("{self:#}[{index:#}]"), self.1).with_meta([index.1])
     }
 
     /// Create a ​keyed composite literal. e.g. `A{a: 1, b: 2, ...}`
     pub fn complit(ty: Type, vals: impl IntoIterator<Item = (impl Display, Expr)>) -> Self {
-        let vals = vals
-            .into_iter()
-            .map(|(name, val)| format!("{name}: {val}"))
-            .collect::<Vec<_>>()
-            .join(", ");
-        Self(format!("{ty}{{{vals}}}"))
+        let vals = vals.into_iter().map(|(name, val)| Self(format!("{name}: {val:#}"), val.1));
+        let vals = Self::join(vals, ", ");
+        Self(format!("{ty}{{{vals:#}}}"), vals.1)
     }
 
     /// Create a ​unkeyed composite literal. e.g. `A{1, 2, ...}`
     pub fn ord_complit(ty: Type, vals: impl IntoIterator<Item = Expr>) -> Self {
-        let vals = vals.into_iter().map(|val| val.to_string()).collect::<Vec<_>>().join(", ");
-        Self(format!("{ty}{{{vals}}}"))
+        let vals = Self::join(vals, ", ");
+        Self(format!("{ty}{{{vals:#}}}"), vals.1)
     }
 
     /// Create a closure. e.g. `func(){}`
-    pub fn closure(
-        args: impl IntoIterator<Item = Type>,
-        rets: impl IntoIterator<Item = Type>,
-        body: Block,
-    ) -> Self {
-        let args = args.into_iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", ");
-        let mut rets = rets.into_iter().map(|r| r.to_string()).collect::<Vec<_>>().join(", ");
-        if !rets.is_empty() {
-            rets = format!(" {rets}")
-        }
-        Self(format!("func({args}){rets} {body}"))
+    pub fn closure(sig: Signature, body: Block) -> Self {
+        Self(format!("func{sig} {body:#}"), body.1)
     }
 
     /// Create a function call expr.
     pub fn call(self, args: impl IntoIterator<Item = Expr>) -> Self {
-        let args: Vec<_> = args.into_iter().map(|e| e.to_string()).collect();
-        Self(format!("{self}({})", args.join(", ")))
+        let args = Self::join(args, ", ");
+        Self(format!("{self:#}({args:#})"), self.1).with_meta([args.1])
     }
 }
 
 impl Neg for Expr {
     type Output = Self;
     fn neg(self) -> Self::Output {
-        Self(format!("-{self}"))
+        Self(format!("-{self:#}"), self.1)
     }
 }
 impl Not for Expr {
     type Output = Self;
     fn not(self) -> Self::Output {
-        Self(format!("!{self}"))
+        Self(format!("!{self:#}"), self.1)
     }
 }
-impl Into<Expr> for String {
-    fn into(self) -> Expr {
-        Expr::str(self)
+impl From<String> for Expr {
+    fn from(value: String) -> Self {
+        Expr::str(value)
     }
 }
-impl Into<Expr> for &str {
-    fn into(self) -> Expr {
-        Expr::str(self)
+impl From<&str> for Expr {
+    fn from(value: &str) -> Self {
+        Expr::str(value)
     }
 }
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        crate::output::fmt_node(f, &self.0)
     }
 }
 
@@ -111,7 +146,7 @@ macro_rules! impl_binop { ($($t:ident, $f:ident, $op:expr),*) => {$(
     }
 )*}}
 macro_rules! impl_into { ($($t:ty),*) => {$(
-    impl Into<Expr> for $t { fn into(self) -> Expr { Expr::raw(self) } }
+    impl From<$t> for Expr { fn from(value: $t) -> Self { Expr::raw(value) } }
 )*}}
 impl_binop!(Add, add, "+", Sub, sub, "-", Mul, mul, "*", Div, div, "/");
 impl_binop!(Rem, rem, "%", BitAnd, bitand, "&", BitOr, bitor, "|");
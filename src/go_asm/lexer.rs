@@ -0,0 +1,185 @@
+//! Tokenizer for rendered Go source, used by the checks that run over a [`Package`].
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Ident,
+    Keyword,
+    /// Number, string, raw string or rune literal.
+    Literal,
+    Punct,
+    /// `;`, explicit or inserted at the end of a line.
+    Semi,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub kind: Kind,
+    pub text: String,
+    /// 1-based line.
+    pub line: usize,
+    /// Byte range in the source.
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Token {
+    pub fn is(&self, text: &str) -> bool {
+        matches!(self.kind, Kind::Keyword | Kind::Punct | Kind::Semi) && self.text == text
+    }
+}
+
+pub(crate) const KEYWORDS: [&str; 25] = [
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+];
+
+const PUNCTS: [&str; 48] = [
+    "<<=", ">>=", "&^=", "...", "&&", "||", "<-", "++", "--", "==", "!=", "<=", ">=", ":=", "+=",
+    "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<", ">>", "&^", "+", "-", "*", "/", "%", "&", "|",
+    "^", "<", ">", "=", "!", "(", ")", "[", "]", "{", "}", ",", ".", ":", "~", ";",
+];
+
+/// Whether `name` is a valid Go identifier that isn't a keyword.
+pub(crate) fn is_ident(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+pub(crate) fn tokenize(src: &str) -> Vec<Token> {
+    let bytes = src.as_bytes();
+    let mut toks: Vec<Token> = Vec::new();
+    let (mut i, mut line) = (0, 1);
+    let push = |toks: &mut Vec<Token>, kind, start, end, line| {
+        toks.push(Token { kind, text: src[start..end].to_string(), line, start, end })
+    };
+    while i < src.len() {
+        let c = src[i..].chars().next().unwrap();
+        let start = i;
+        match c {
+            '\n' => {
+                if needs_semi(toks.last()) {
+                    toks.push(Token { kind: Kind::Semi, text: "\n".into(), line, start, end: i });
+                }
+                line += 1;
+                i += 1;
+            }
+            c if c.is_whitespace() => i += c.len_utf8(),
+            '/' if src[i..].starts_with("//") => {
+                i = src[i..].find('\n').map_or(src.len(), |n| i + n);
+            }
+            '/' if src[i..].starts_with("/*") => {
+                let end = src[i + 2..].find("*/").map_or(src.len(), |n| i + n + 4);
+                let newlines = src[i..end].matches('\n').count();
+                // a comment spanning lines acts like a newline.
+                if newlines > 0 && needs_semi(toks.last()) {
+                    toks.push(Token { kind: Kind::Semi, text: "\n".into(), line, start, end: i });
+                }
+                line += newlines;
+                i = end;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let len = src[i..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(src.len() - i);
+                i += len;
+                let kind =
+                    if KEYWORDS.contains(&&src[start..i]) { Kind::Keyword } else { Kind::Ident };
+                push(&mut toks, kind, start, i, line);
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit())) =>
+            {
+                i += 1;
+                let hex = src[start..].starts_with("0x") || src[start..].starts_with("0X");
+                while let Some(&b) = bytes.get(i) {
+                    let exp = match bytes[i - 1] {
+                        b'e' | b'E' => !hex,
+                        b'p' | b'P' => hex,
+                        _ => false,
+                    };
+                    let sign = exp && (b == b'+' || b == b'-');
+                    if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || sign {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                push(&mut toks, Kind::Literal, start, i, line);
+            }
+            '"' | '\'' | '`' => {
+                // a raw string may span lines, it is on the line it starts.
+                let first = line;
+                i += 1;
+                while i < src.len() {
+                    let b = bytes[i];
+                    if b == b'\\' && c != '`' {
+                        i += 2;
+                        continue;
+                    }
+                    if b == b'\n' {
+                        if c != '`' {
+                            break;
+                        }
+                        line += 1;
+                    }
+                    i += 1;
+                    if b == c as u8 {
+                        break;
+                    }
+                }
+                i = i.min(src.len());
+                push(&mut toks, Kind::Literal, start, i, first);
+            }
+            _ => {
+                let len = PUNCTS
+                    .iter()
+                    .find(|p| src[i..].starts_with(**p))
+                    .map_or(c.len_utf8(), |p| p.len());
+                i += len;
+                let kind = if &src[start..i] == ";" { Kind::Semi } else { Kind::Punct };
+                push(&mut toks, kind, start, i, line);
+            }
+        }
+    }
+    if needs_semi(toks.last()) {
+        toks.push(Token { kind: Kind::Semi, text: "\n".into(), line, start: i, end: i });
+    }
+    toks.push(Token { kind: Kind::Eof, text: String::new(), line, start: i, end: i });
+    toks
+}
+
+/// Automatic semicolon insertion rule of the Go spec.
+fn needs_semi(last: Option<&Token>) -> bool {
+    last.is_some_and(|t| match t.kind {
+        Kind::Ident | Kind::Literal => true,
+        Kind::Keyword => ["break", "continue", "fallthrough", "return"].contains(&t.text.as_str()),
+        Kind::Punct => ["++", "--", ")", "]", "}"].contains(&t.text.as_str()),
+        _ => false,
+    })
+}
@@ -0,0 +1,48 @@
+use super::*;
+use std::collections::BTreeSet;
+
+/// Information carried alongside the code of a [`Stmt`], [`Block`] or [`Decl`], merged into the
+/// enclosing node when it is pushed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Meta {
+    /// Packages the code refers to, hoisted into the import block of the [`Package`].
+    pub imports: BTreeSet<String>,
+    /// Package directories of the enclosing [`Module`] the code refers to, e.g. `api`, imported
+    /// by their path in the module when it is rendered.
+    pub module_imports: BTreeSet<String>,
+    /// Names the node declares in its enclosing scope, see [`crate::naming::Scope`]. Not merged,
+    /// bindings of a nested block stay in that block.
+    pub bindings: BTreeSet<String>,
+}
+
+impl Meta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn import(mut self, lib: impl Display) -> Self {
+        self.imports.insert(lib.to_string());
+        self
+    }
+
+    /// Import of the package in `dir` of the enclosing [`Module`], resolved to the module path
+    /// when the module is rendered. e.g. `api` -> `github.com/acme/sdk/api`
+    pub fn module_import(mut self, dir: impl Display) -> Self {
+        self.module_imports.insert(dir.to_string().trim_matches('/').to_string());
+        self
+    }
+
+    pub fn bind(mut self, name: impl Display) -> Self {
+        let name = name.to_string();
+        if name != "_" {
+            self.bindings.insert(name);
+        }
+        self
+    }
+
+    pub fn merge(&mut self, other: Meta) -> &mut Self {
+        self.imports.extend(other.imports);
+        self.module_imports.extend(other.module_imports);
+        self
+    }
+}
@@ -1,13 +1,31 @@
+pub mod errflow;
+pub mod error;
 pub mod exprs;
+mod lexer;
+pub mod meta;
+pub mod module;
+pub mod naming;
 pub mod package;
 pub mod stmts;
+pub mod testing;
+pub mod typeck;
 pub mod types;
+pub mod validate;
+mod walker;
 
+pub use errflow::ErrFlow;
+pub use error::Error;
 pub use exprs::Expr;
-pub use package::{Decl, Package};
+pub use meta::Meta;
+pub use module::Module;
+pub use package::{Decl, Enum, Method, Package, TypeDecl, ValueSpec};
+pub(super) use crate::naming::Bindings;
 pub(super) use std::fmt::{Display, Write};
-pub use stmts::{Block, Stmt};
-pub use types::{IfaceFn, Type};
+pub use stmts::{Block, Case, Range, Stmt, Switch};
+pub use testing::TableTest;
+pub use typeck::Externs;
+pub use types::{Field, IfaceFn, Param, Signature, Tag, Tags, Type};
+pub use validate::{Diagnostic, DiagnosticKind};
 
 mod tests {
     #[test]
@@ -17,7 +35,538 @@ mod tests {
             .push(Expr::raw("fmt").attr("PrintLn").call(["Hello World".into()]).into());
         let pkg = Package::new("main")
             .push(Decl::import("fmt"))
-            .push(Decl::func("main", [], [], main_body));
+            .push(Decl::func("main", Signature::new(), main_body));
         print!("{pkg}")
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn signature() {
+        use super::*;
+        let sig = Signature::new()
+            .param("a", Type::string())
+            .variadic("rest", Type::any())
+            .result("n", Type::int(64))
+            .result("", Type::raw("error"));
+        assert_eq!(sig.to_string(), "(a string, rest ...any) (n int64, _ error)");
+        let sig = Signature::new()
+            .param("", Type::bool())
+            .results([("", Type::string()), ("", Type::bool())]);
+        assert_eq!(Type::fn_ptr(sig).to_string(), "func(bool) (string, bool)");
+        let sig = Signature::new().result("", Type::raw("error"));
+        assert_eq!(IfaceFn::new("Close", sig).to_string(), "Close() error");
+    }
+
+    #[test]
+    fn struct_tags() {
+        use super::*;
+        let id = Field::new("UserID", Type::string())
+            .snake_tag("json", ["omitempty"])
+            .and_then(|f| f.tag("db", "id", Vec::<&str>::new()))
+            .unwrap();
+        assert_eq!(id.to_string(), "UserID string `json:\"user_id,omitempty\" db:\"id\"`");
+        assert!(id.clone().tag("db", "x", Vec::<&str>::new()).is_err());
+        assert!(id.clone().tag("bad key", "x", Vec::<&str>::new()).is_err());
+        let err = id.clone().tag("a`b", "x", Vec::<&str>::new()).err();
+        assert_eq!(err, Some(Error::InvalidTagKey("a`b".into())));
+        let err = id.clone().tag("xml", "a`b", Vec::<&str>::new()).err();
+        assert_eq!(err, Some(Error::InvalidTagValue("a`b".into())));
+        let quoted = Tags::new().tag("validate", r#"oneof="a b""#, [r#"x\"y"#]).unwrap();
+        assert_eq!(quoted.to_string(), r#"`validate:"oneof=\"a b\",x\\\"y"`"#);
+        let ty = Type::struct_(Vec::<&str>::new(), [id, ("HTTPServer", Type::string()).into()]);
+        assert!(ty.to_string().contains("\nHTTPServer string\n"));
+    }
+
+    #[test]
+    fn type_decl() {
+        use super::*;
+        let string_sig = Signature::new().result("", Type::string());
+        let stringer = [IfaceFn::new("String", string_sig.clone())];
+        let ty = Type::struct_(Vec::<&str>::new(), [("X", Type::int(64))]);
+        let point = TypeDecl::new("Point", ty)
+            .ptr_method("String", string_sig, Block::new().push(Stmt::ret(["".into()])))
+            .unwrap();
+        assert!(point.satisfies(&stringer, true).is_ok());
+        assert_eq!(point.satisfies(&stringer, false), Err(Error::PointerReceiver("String".into())));
+        let closer = [IfaceFn::new("Close", Signature::new().result("", Type::raw("error")))];
+        assert_eq!(point.satisfies(&closer, true), Err(Error::MissingMethod("Close".into())));
+        let decl: Decl = point.into();
+        assert!(decl.to_string().contains("func (p *Point) String() string {"));
+    }
+
+    #[test]
+    fn enum_() {
+        use super::*;
+        let color = Enum::new("Color", Type::raw("int")).variant("Red").variant("Green");
+        let decl: Decl = color.text_marshaling().into();
+        assert!(decl.1.imports.contains("fmt"));
+        let decl = decl.to_string();
+        assert!(decl.starts_with("type Color int\nconst (\n    Red Color = iota\n    Green\n)\n"));
+        let string = "func (c Color) String() string {\n    switch c {\ncase Red:\n    \
+            return \"Red\"\ncase Green:\n    return \"Green\"\n}\n    \
+            return fmt.Sprintf(\"Color(%d)\", int(c))\n}\n";
+        assert!(decl.contains(string));
+        let marshal = "func (c Color) MarshalText() ([]byte, error) {\n    \
+            return []byte(c.String()), nil\n}\n";
+        assert!(decl.contains(marshal));
+        assert!(decl.contains("func (c *Color) UnmarshalText(text []byte) error {"));
+        assert!(decl.contains("case \"Green\":\n    *c = Green\n    return nil\n}"));
+        assert!(decl.ends_with("    return fmt.Errorf(\"invalid Color: %q\", text)\n}"));
+    }
+
+    #[test]
+    fn stmts() {
+        use super::*;
+        assert_eq!(Stmt::op_assign(Expr::raw("x"), "<<", 1.into()).to_string(), "x <<= 1");
+        assert_eq!(Stmt::inc(Expr::raw("i")).to_string(), "i++");
+        let ch = Expr::raw("ch");
+        let sel = Stmt::select(
+            [
+                (Stmt::variable("v", ch.clone().recv()), Block::new().push(Stmt::brk_label("L"))),
+                (Stmt::send(ch, 1.into()), Block::new()),
+            ],
+            Some(Block::new()),
+        );
+        let sel = Stmt::label("L", Stmt::for_(None, None, None, Block::new().push(sel)).unwrap());
+        assert_eq!(
+            sel.to_string(),
+            "L: for {\n    select {\ncase v := <-ch:\n    break L\ncase ch <- 1:\ndefault:\n}\n}"
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        use super::*;
+        let i = Expr::raw("i");
+        let body = Block::new;
+        let cond = Some(i.clone().binop("<", 10.into()));
+        let for_ = Stmt::for_(None, cond.clone(), Some(Stmt::inc(i.clone())), body()).unwrap();
+        assert_eq!(for_.to_string(), "for ; (i < 10); i++ {\n}");
+        let for_ = Stmt::for_(Some(Stmt::variable("i", 0.into())), None, None, body()).unwrap();
+        assert_eq!(for_.to_string(), "for i := 0; ; {\n}");
+        let define = Some(Stmt::variable("j", 0.into()));
+        assert!(Stmt::for_(None, cond.clone(), define, body()).is_err());
+        assert!(Stmt::for_(Some(Stmt::ret([])), cond.clone(), None, body()).is_err());
+        let raw = Some(Stmt::raw(r"s += `\` + `a:b`"));
+        assert!(Stmt::for_(None, cond, raw, body()).is_ok());
+        let at = "api.yaml:12:3";
+        let init = Some(Stmt::variable("i", 0.into()).origin(at));
+        assert!(Stmt::for_(init, None, None, body()).is_ok());
+        let ret = Some(Stmt::raw("return").origin(at));
+        assert!(Stmt::for_(ret, None, None, body()).is_err());
+        let pkg = Package::new("main").push(Decl::import("fmt").origin(at));
+        assert_eq!(pkg.to_string(), "package main\nimport \"fmt\"\n");
+        let stmt = Stmt::inc(i).origin(at);
+        let block = Block::new().push(stmt.clone());
+        let func = Decl::func("f", Signature::new(), block.clone()).origin("api.yaml:1:1");
+        assert_eq!(stmt.to_string(), "i++");
+        assert_eq!(block.to_string(), "{\n    i++\n}");
+        assert_eq!(func.to_string(), "func f() {\n    i++\n}");
+        let (src, map) = Package::new("main").push(func).source_map("main.go");
+        assert_eq!(src, "package main\nfunc f() {\n    i++\n}\n");
+        assert_eq!(map.origin(1, 0), Some("api.yaml:1:1"));
+        assert_eq!(map.origin(2, 4), Some(at));
+
+        let range = Range::over(Expr::raw("m")).key("k").value("v").body(body());
+        assert_eq!(range.to_string(), "for k, v := range m {\n}");
+        assert_eq!(Range::over(10.into()).body(body()).to_string(), "for range 10 {\n}");
+        let range = Range::over(Expr::raw("seq")).value("x").assign().body(body());
+        assert_eq!(range.to_string(), "for _, x = range seq {\n}");
+    }
+
+    #[test]
+    fn switch() {
+        use super::*;
+        let ret = |v: &str| Block::new().push(Stmt::ret([v.into()]));
+        let sw = Switch::on_type("v", Expr::raw("x"))
+            .case([Type::int(64).into(), Type::string().into()], ret("scalar"))
+            .case([Expr::raw("nil")], ret("nil"))
+            .default(ret("other"));
+        assert_eq!(
+            sw.clone().build().unwrap().to_string(),
+            "switch v := x.(type) {\ncase int64, string:\n    return \"scalar\"\ncase nil:\n    \
+            return \"nil\"\ndefault:\n    return \"other\"\n}"
+        );
+        assert_eq!(sw.fallthrough().build().err(), Some(Error::FallthroughInTypeSwitch));
+        let empty = Switch::on(Expr::raw("x")).case([], ret("none")).default(ret("other"));
+        assert_eq!(empty.build().err(), Some(Error::EmptyCase));
+
+        let x = Expr::raw("x");
+        let sw = Switch::new()
+            .init(Stmt::variable("x", Expr::raw("f").call([])))
+            .case([x.clone().binop(">", 0.into())], Block::new())
+            .fallthrough();
+        assert_eq!(sw.clone().build().err(), Some(Error::FallthroughInLastCase));
+        let sw = sw.case([x.binop("<", 0.into())], Block::new()).build().unwrap();
+        assert!(sw.to_string().starts_with("switch x := f(); {\ncase (x > 0):\n    fallthrough\n"));
+    }
+
+    #[test]
+    fn errflow() {
+        use super::*;
+        let sig = Signature::new().result("", Type::raw("*Config")).result("", Type::raw("error"));
+        let flow = ErrFlow::new(&sig).unwrap();
+        let body = Block::new()
+            .push(Stmt::variables(["f", "err"], [Expr::raw("os.Open").call([Expr::raw("path")])]))
+            .push(flow.wrap("err", "open config"))
+            .push(ErrFlow::is(Expr::raw("err"), Expr::raw("ErrEmpty"), Block::new()));
+        let pkg = Package::new("config")
+            .push(Decl::import("os"))
+            .push(Decl::sentinel("ErrEmpty", "empty config"))
+            .push(Decl::func("Load", sig.param("path", Type::string()), body));
+        let pkg = pkg.to_string();
+        let imports = "import (\n    \"errors\"\n    \"fmt\"\n    \"os\"\n)\n";
+        assert!(pkg.starts_with(&format!("package config\n{imports}var ErrEmpty = ")));
+        assert!(pkg.contains("return nil, fmt.Errorf(\"open config: %w\", err)"));
+        assert!(ErrFlow::new(&Signature::new()).is_err());
+        assert_eq!(Type::int(32).zero().to_string(), "0");
+        assert_eq!(Type::raw("Config").zero().to_string(), "*new(Config)");
+
+        // imports of closures and composite literals reach the package.
+        let sig = Signature::new().result("", Type::raw("error"));
+        let flow = ErrFlow::new(&sig).unwrap();
+        let worker = Expr::closure(Signature::new(), Block::new().push(flow.fail("stop")));
+        let log = Expr::raw("log.Print").with_import("log").call([Expr::raw("x")]);
+        let cleanup = Expr::closure(Signature::new(), Block::new().push(log.into()));
+        let timeout = Expr::complit(
+            Type::raw("Options"),
+            [("Timeout", Expr::raw("time.Second").with_import("time"))],
+        );
+        let body = Block::new()
+            .push(Stmt::go(worker.call([])))
+            .push(Stmt::defer(cleanup.call([])))
+            .push(Stmt::variable("opts", timeout))
+            .push(flow.wrap("err", "at 100%"));
+        let pkg = Package::new("run").push(Decl::func("Run", sig, body)).to_string();
+        let imports = "import (\n    \"errors\"\n    \"fmt\"\n    \"log\"\n    \"time\"\n)\n";
+        assert!(pkg.starts_with(&format!("package run\n{imports}")));
+        assert!(pkg.contains("return fmt.Errorf(\"at 100%%: %w\", err)"));
+
+        let ret = Block::new().push(Stmt::ret([Expr::raw("pathErr.Path")]));
+        let [var, if_] = ErrFlow::as_(Expr::raw("err"), "pathErr", Type::raw("*fs.PathError"), ret);
+        assert!(var.1.bindings.contains("pathErr") && if_.1.imports.contains("errors"));
+        let block = Block::new().push(var).push(if_);
+        let as_ = "    var pathErr *fs.PathError\n    if errors.As(err, &pathErr) {";
+        assert!(block.0.starts_with(as_));
+
+        let sentinel = Decl::sentinel("ErrPath", "bad C:\\path\n\t\u{1}");
+        assert_eq!(sentinel.0, r#"var ErrPath = errors.New("bad C:\\path\n\t\u0001")"#);
+        assert!(Package::new("fs").push(sentinel).validate().is_ok());
+        let raw = Package::new("fs").push(Decl::raw("var s = \"a\nb\""));
+        let diags = raw.validate().unwrap_err();
+        assert_eq!(diags[0].to_string(), "2: string literal not terminated");
+    }
+
+    #[test]
+    fn validate() {
+        use super::*;
+        let toks = lexer::tokenize("x := a /* c\n */ y := `r\ns` + z");
+        let toks: Vec<_> = toks.iter().map(|t| (t.text.as_str(), t.line)).collect();
+        assert_eq!(toks[3..7], [("\n", 1), ("y", 2), (":=", 2), ("`r\ns`", 2)]);
+        assert_eq!(toks[8], ("z", 3));
+        let sig = Signature::new().param("xs", Type::raw("[]int")).result("", Type::raw("int"));
+        let body = Block::new()
+            .push(Stmt::variable("total", Expr::raw("0")))
+            .push(Range::over(Expr::raw("xs")).key("_").value("x").body(
+                Block::new().push(Stmt::op_assign(Expr::raw("total"), "+", Expr::raw("x"))),
+            ))
+            .push(Stmt::ret([Expr::raw("total")]));
+        let pkg = Package::new("sum").push(Decl::func("Sum", sig.clone(), body));
+        assert_eq!(pkg.validate(), Ok(()));
+
+        let body = Block::new()
+            .push(Stmt::variable("unused", Expr::raw("1")))
+            .push(Stmt::brk());
+        let pkg = pkg
+            .push(Decl::func("Sum", sig, body))
+            .push(Decl::raw("var user-id int"))
+            .push(Decl::raw("func f(type int) {}"));
+        let diags = pkg.validate().unwrap_err();
+        let kinds: Vec<_> = diags.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DiagnosticKind::Duplicate,
+                DiagnosticKind::MisplacedBranch,
+                DiagnosticKind::MissingReturn,
+                DiagnosticKind::UnusedVar,
+                DiagnosticKind::InvalidIdent,
+                DiagnosticKind::KeywordIdent,
+            ]
+        );
+        let msg = "func Sum: break is not in a loop, switch, or select";
+        assert!(diags[1].to_string().ends_with(msg));
+    }
+
+    #[test]
+    fn naming() {
+        use super::naming::*;
+        assert_eq!(exported("user_id"), "UserID");
+        assert_eq!(exported("http-server"), "HTTPServer");
+        assert_eq!(exported("2fa_enabled"), "X2faEnabled");
+        assert_eq!(unexported("HTTPServer"), "httpServer");
+        assert_eq!(unexported("user id"), "userID");
+        assert_eq!(unexported("type"), "type_");
+        assert_eq!(unexported("2fa"), "_2fa");
+        assert_eq!(ident("user-id"), "user_id");
+
+        assert_eq!(crate::naming::unique("x", |name| name != "x_2"), "x_2");
+    }
+
+    #[test]
+    fn scope() {
+        use super::*;
+        use crate::naming::Scope;
+        let sig = Signature::new().param("tmp", Type::raw("int")).result("err", Type::raw("error"));
+        let mut scope = Scope::new();
+        scope.enter_with(&sig);
+        let mut body = Block::new();
+        body.pushs(scope.track(Stmt::variables(["n", "_"], [Expr::raw("f()"), Expr::raw("g()")])));
+        assert!(scope.is_bound("n") && scope.is_bound("err") && !scope.is_bound("_"));
+        assert_eq!(scope.fresh_name("tmp"), "tmp_1");
+
+        let range = Range::over(Expr::raw("xs")).value("n");
+        scope.enter_with(&range);
+        let tmp = scope.fresh_name("tmp");
+        let inner = Block::new().push(Stmt::variable(&tmp, Expr::raw("n")));
+        scope.leave();
+        body.pushs(range.body(inner));
+        assert_eq!(tmp, "tmp_2");
+        assert_eq!(scope.shadowing().len(), 1);
+        assert_eq!(scope.shadowing()[0].to_string(), "`n` shadows a binding of an enclosing scope");
+        assert!(Stmt::block([Stmt::variable("x", 1.into())]).1.bindings.is_empty());
+    }
+
+    #[test]
+    fn check_types() {
+        use super::*;
+        let sig = Signature::new()
+            .param("a", Type::int(32))
+            .param("b", Type::int(64))
+            .result("", Type::raw("time.Duration"));
+        let body = Block::new()
+            .push(Stmt::variable("sum", Expr::raw("a").binop("+", Expr::raw("b"))))
+            .push(Stmt::assign(Expr::raw("b"), Expr::raw("a")))
+            .push(Stmt::raw("var small int8 = 300"))
+            .push(Stmt::ret([Expr::raw("time.Second").binop("*", Expr::raw("sum"))]));
+        let pkg = Package::new("calc").push(Decl::func("Scale", sig, body));
+        let externs = Externs::new()
+            .named("time.Duration", Type::int(64))
+            .value("time.Second", Type::raw("time.Duration"));
+        let diags = pkg.check_types(&externs).unwrap_err();
+        let kinds: Vec<_> = diags.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DiagnosticKind::TypeMismatch,
+                DiagnosticKind::NeedsConversion,
+                DiagnosticKind::Overflow,
+                DiagnosticKind::TypeMismatch,
+            ]
+        );
+        let msg = "cannot use a (int32) as int64 value in assignment, needs conversion int64(a)";
+        assert!(diags[1].to_string().ends_with(msg));
+
+        let sig = Signature::new().param("n", Type::int(64)).result("", Type::raw("time.Duration"));
+        let body = Block::new().push(Stmt::ret([
+            Expr::raw("time.Duration(n)").binop("*", Expr::raw("time.Second"))
+        ]));
+        let pkg = Package::new("calc").push(Decl::func("Seconds", sig, body));
+        assert_eq!(pkg.check_types(&externs), Ok(()));
+
+        // constants beyond 128 bits are left unchecked rather than wrapped.
+        let pkg = Package::new("calc")
+            .push(Decl::raw("const c = 1 << 127"))
+            .push(Decl::raw("const d = -c >> 120"))
+            .push(Decl::raw("var x int = d"))
+            .push(Decl::raw("var y int8 = 1 << 126 >> 118"));
+        let diags = pkg.check_types(&Externs::new()).unwrap_err();
+        assert_eq!(diags.iter().map(|d| d.kind).collect::<Vec<_>>(), [DiagnosticKind::Overflow]);
+    }
+
+    #[test]
+    fn test_file() {
+        use super::*;
+        let fields = [("in", Type::string()), ("want", Type::raw("int"))];
+        let body = Block::new().push(Stmt::raw(
+            "if got := calc.Parse(tt.in); got != tt.want {\nt.Errorf(\"got %d\", got)\n}",
+        ));
+        let named = TableTest::new("Parse", [("name", Type::string())]);
+        assert_eq!(Decl::try_from(named).err(), Some(Error::TestCaseName));
+        let table = TableTest::new("Parse", fields.clone());
+        let table = table.case("one", [Expr::str("1"), 1.into()]);
+        let table = table.unwrap().body(body);
+        assert_eq!(
+            TableTest::new("Parse", fields).case("one", []).err(),
+            Some(Error::TestCaseValues("one".into()))
+        );
+        let call = Stmt::raw("calc.Parse(\"1\")");
+        let print = Stmt::raw("fmt.Println(calc.Parse(\"42\"))").with_import("fmt");
+        let pkg = Package::external_test("example.com/calc")
+            .push(table.try_into().unwrap())
+            .push(Decl::benchmark("Parse", Block::new(), Block::new().push(call.clone())))
+            .push(Decl::fuzz("Parse", [[Expr::str("1")]], [("s", Type::string())], Block::new()))
+            .push(Decl::example("Parse", Block::new().push(print), "42"));
+        let src = pkg.to_string();
+        let imports = "import (\n    \"example.com/calc\"\n    \"fmt\"\n    \"testing\"\n)";
+        assert!(src.starts_with(&format!("package calc_test\n{imports}")));
+        assert!(src.contains("    {name: \"one\", in: \"1\", want: 1},\n"));
+        assert!(src.contains("t.Run(tt.name, func(t *testing.T) {"));
+        assert!(src.contains("func BenchmarkParse(b *testing.B) {\n    for b.Loop() {"));
+        assert!(src.contains("f.Add(\"1\")\n    f.Fuzz(func(t *testing.T, s string) {"));
+        assert!(src.ends_with("    // Output:\n    // 42\n}\n"));
+        assert_eq!(pkg.validate(), Ok(()));
+    }
+
+    #[test]
+    fn module() {
+        use super::*;
+        use crate::output::RegionError;
+        let client =
+            Decl::raw("type Client struct{ Store *store.Store }").with_module_import("store");
+        let store = Decl::raw("type Store struct{ ID uuid.UUID }")
+            .with_import("github.com/google/uuid");
+        let sentinel = Decl::sentinel("ErrMissing", "missing");
+        let store_test = Package::external_test("github.com/acme/sdk/store");
+        let module = Module::new("github.com/acme/sdk", "1.22")
+            .require("github.com/google/uuid", "v1.6.0")
+            .file("", "client.go", Package::new("sdk").push(client))
+            .and_then(|m| m.file("store", "store.go", Package::new("store").push(store)))
+            .and_then(|m| m.file("store", "store_test.go", store_test))
+            .and_then(|m| m.file("errors", "errors.go", Package::new("errors")))
+            .and_then(|m| m.file("", "errs.go", Package::new("sdk").push(sentinel)))
+            .unwrap();
+        let pkg = Package::new("other");
+        assert_eq!(
+            module.clone().file("store", "other.go", pkg.clone()).err(),
+            Some(Error::MixedPackages("store".into()))
+        );
+        assert_eq!(
+            module.clone().file("../x", "x.go", pkg.clone()).err(),
+            Some(Error::InvalidPath("../x/x.go".into()))
+        );
+        assert_eq!(
+            module.clone().file("", "go.mod", pkg).err(),
+            Some(Error::InvalidPath("go.mod".into()))
+        );
+
+        let files = module.render();
+        let paths: Vec<_> = files.keys().map(String::as_str).collect();
+        let expected = ["client.go", "errors/errors.go", "errs.go", "go.mod"];
+        assert_eq!(paths[..4], expected);
+        assert_eq!(paths[4..], ["store/store.go", "store/store_test.go"]);
+        assert_eq!(
+            files["go.mod"],
+            "module github.com/acme/sdk\n\ngo 1.22\n\nrequire github.com/google/uuid v1.6.0\n"
+        );
+        let import = "package sdk\nimport \"github.com/acme/sdk/store\"";
+        assert!(files["client.go"].starts_with(import));
+        assert!(files["store/store.go"].contains("import \"github.com/google/uuid\""));
+        assert!(files["errs.go"].contains("import \"errors\""));
+        let marked = Package::new("sdk").push(Decl::raw("var _ any").with_module_import("store"));
+        assert_eq!(marked.to_string(), "package sdk\nvar _ any\n");
+        let diags = marked.validate().unwrap_err();
+        assert_eq!(diags[0].kind, DiagnosticKind::ModuleImport);
+
+        let dir = std::env::temp_dir().join(format!("codeasm-module-{}", std::process::id()));
+        module.save(&dir).unwrap();
+        std::fs::write(dir.join("stale.go"), "").unwrap();
+        module.save(&dir).unwrap();
+        assert!(!dir.join("stale.go").exists());
+        let saved = std::fs::read_to_string(dir.join("store/store.go")).unwrap();
+        assert_eq!(saved, files["store/store.go"]);
+        module.save(dir.join("store/..")).unwrap();
+        let custom = Decl::region("custom", [Decl::raw("func helper() {}")]);
+        let regions = module.clone().file("", "custom.go", Package::new("sdk").push(custom));
+        regions.unwrap().save(&dir).unwrap();
+        let err = module.save(&dir).unwrap_err();
+        let err = err.into_inner().unwrap().downcast::<RegionError>().unwrap();
+        assert!(matches!(*err, RegionError::Missing { region, .. } if region == "custom"));
+        assert!(dir.join("custom.go").exists());
+        let hand = std::env::temp_dir().join(format!("codeasm-hand-{}", std::process::id()));
+        std::fs::create_dir_all(&hand).unwrap();
+        std::fs::write(hand.join("main.go"), "").unwrap();
+        let refused = module.save(&hand).unwrap_err();
+        assert_eq!(refused.kind(), std::io::ErrorKind::AlreadyExists);
+        assert!(hand.join("main.go").exists());
+        std::fs::remove_dir_all(&hand).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sync() {
+        use super::*;
+        use crate::output::{MANIFEST, SaveReport};
+        let dir = std::env::temp_dir().join(format!("codeasm-sync-{}", std::process::id()));
+        let a = Package::new("app").push(Decl::raw("var A = 1"));
+        let b = Package::new("util").push(Decl::raw("var B = 2"));
+        let module = Module::new("example.com/app", "1.22").file("", "a.go", a.clone());
+        let module = module.and_then(|m| m.file("util", "b.go", b));
+        let module = module.and_then(|m| m.file("", "c.go", Package::new("app"))).unwrap();
+        module.save(&dir).unwrap();
+        std::fs::write(dir.join("notes.txt"), "kept").unwrap();
+
+        let report = module.sync(&dir, true).unwrap();
+        assert!(!report.is_modified());
+        assert_eq!(report.unchanged, ["a.go", "c.go", "go.mod", "util/b.go"]);
+
+        let a = a.push(Decl::raw("var C = 3"));
+        let next = Module::new("example.com/app", "1.22").file("", "a.go", a).unwrap();
+        let report = next.sync(&dir, false).unwrap();
+        assert_eq!(report.changed, ["a.go"]);
+        assert_eq!(report.stale, ["c.go", "util/b.go"]);
+        assert!(report.removed.is_empty() && dir.join("util/b.go").exists());
+        assert!(!next.sync(&dir, false).unwrap().is_modified());
+        std::fs::write(dir.join("c.go"), "package app\n\nvar D = 4\n").unwrap();
+        let report = next.sync(&dir, true).unwrap();
+        assert_eq!(
+            report,
+            SaveReport {
+                unchanged: vec!["a.go".into(), "go.mod".into()],
+                removed: vec!["util/b.go".into()],
+                edited: vec!["c.go".into()],
+                ..Default::default()
+            }
+        );
+        assert!(!dir.join("util").exists() && dir.join("notes.txt").exists());
+        assert!(dir.join("c.go").exists());
+        let manifest = std::fs::read_to_string(dir.join(MANIFEST)).unwrap();
+        assert_eq!(manifest.lines().count(), 4);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn header() {
+        use super::*;
+        use crate::output::Header;
+        let schema = r#"{"definitions": {"User": {}}}"#;
+        let header = Header::new("sdkgen")
+            .version("v1.4.0")
+            .license("Copyright 2024 Acme Inc.\nSPDX-License-Identifier: MIT")
+            .input("api.json", schema);
+        let pkg = Package::new("api").header(header.clone());
+        let src = pkg.to_string();
+        assert_eq!(
+            src.lines().take(6).collect::<Vec<_>>(),
+            [
+                "// Copyright 2024 Acme Inc.",
+                "// SPDX-License-Identifier: MIT",
+                "",
+                "// Code generated by sdkgen v1.4.0 from api.json. DO NOT EDIT.",
+                &format!("// Input hash: fnv1a:{:016x}", header.input.as_ref().unwrap().1),
+                "",
+            ]
+        );
+        assert!(src.ends_with("\npackage api\n"));
+        assert!(Header::is_current(&src, schema));
+        assert!(!Header::is_current(&src, "{}"));
+        assert!(!Header::is_current(&Package::new("api").to_string(), schema));
+
+        let module = Module::new("github.com/acme/sdk", "1.22").header(Header::new("sdkgen"));
+        let own = Package::new("api").header(Header::new("other"));
+        let module = module.file("api", "a.go", own).unwrap();
+        let module = module.file("api", "b.go", Package::new("api")).unwrap();
+        let files = module.render();
+        assert!(files["api/a.go"].starts_with("// Code generated by other. DO NOT EDIT.\n\n"));
+        assert!(files["api/b.go"].starts_with("// Code generated by sdkgen. DO NOT EDIT.\n\n"));
+    }
+}
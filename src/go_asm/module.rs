@@ -0,0 +1,133 @@
+use super::*;
+use std::collections::BTreeMap;
+use crate::output::{Header, SaveReport};
+use std::path::Path;
+
+/// A Go module: `go.mod` and the files of its packages. e.g.
+/// ```go
+/// module github.com/acme/sdk
+///
+/// go 1.22
+///
+/// require github.com/google/uuid v1.6.0
+/// ```
+///
+/// Imports recorded with `with_module_import`, e.g. `api` or `internal/store`, are resolved to
+/// the module path, e.g. `github.com/acme/sdk/api`. Other imports are kept as they are, so a
+/// package directory named like a standard package doesn't shadow it.
+#[derive(Clone)]
+pub struct Module {
+    /// Module path. e.g. `github.com/acme/sdk`
+    pub path: String,
+    /// Go version. e.g. `1.22`
+    pub go: String,
+    /// Required module paths and their versions.
+    pub requires: BTreeMap<String, String>,
+    /// Files by path relative to the module root. e.g. `api/client.go`
+    pub files: BTreeMap<String, Package>,
+    /// Header of the files that don't have their own.
+    pub header: Option<Header>,
+}
+
+impl Module {
+    pub fn new(path: impl Display, go: impl Display) -> Self {
+        Self {
+            path: path.to_string(),
+            go: go.to_string(),
+            requires: BTreeMap::new(),
+            files: BTreeMap::new(),
+            header: None,
+        }
+    }
+
+    /// Mark every file of the module as generated, see [`Package::header`].
+    pub fn header(self, header: Header) -> Self {
+        Self { header: Some(header), ..self }
+    }
+
+    /// e.g. `require("github.com/google/uuid", "v1.6.0")`
+    pub fn require(mut self, path: impl Display, version: impl Display) -> Self {
+        self.requires.insert(path.to_string(), version.to_string());
+        self
+    }
+
+    /// Add a file to the package in `dir`, empty for the module root. Its name must end in `.go`,
+    /// and files of one directory must share the package name, or add `_test` to it.
+    pub fn file(mut self, dir: &str, name: &str, pkg: Package) -> Result<Self, Error> {
+        let dir = dir.trim_matches('/');
+        let path = if dir.is_empty() { name.to_string() } else { format!("{dir}/{name}") };
+        let go = name.strip_suffix(".go").is_some_and(|stem| !stem.is_empty());
+        if !crate::output::is_contained(&path) || !go || name.contains('/') {
+            return Err(Error::InvalidPath(path));
+        }
+        let base = pkg.name.trim_end_matches("_test");
+        let mixed = self.files.iter().any(|(other, p)| {
+            Self::dir_of(other) == dir && p.name.trim_end_matches("_test") != base
+        });
+        if mixed {
+            return Err(Error::MixedPackages(dir.to_string()));
+        }
+        self.files.insert(path, pkg);
+        Ok(self)
+    }
+
+    fn dir_of(path: &str) -> &str {
+        path.rsplit_once('/').map_or("", |(dir, _)| dir)
+    }
+
+    /// Import path of the package in `dir`. e.g. `api` -> `github.com/acme/sdk/api`
+    pub fn import_path(&self, dir: &str) -> String {
+        let dir = dir.trim_matches('/');
+        if dir.is_empty() { self.path.clone() } else { format!("{}/{dir}", self.path) }
+    }
+
+    pub fn go_mod(&self) -> String {
+        let mut res = format!("module {}\n\ngo {}\n", self.path, self.go);
+        match self.requires.len() {
+            0 => {}
+            1 => {
+                let (path, version) = self.requires.first_key_value().unwrap();
+                writeln!(res, "\nrequire {path} {version}").unwrap();
+            }
+            _ => {
+                res.push_str("\nrequire (\n");
+                for (path, version) in &self.requires {
+                    writeln!(res, "    {path} {version}").unwrap();
+                }
+                res.push_str(")\n");
+            }
+        }
+        res
+    }
+
+    /// Contents of every file by path relative to the module root, `go.mod` included. This is
+    /// what [`Module::save`] writes, for a dry run.
+    pub fn render(&self) -> BTreeMap<String, String> {
+        let mut res = BTreeMap::from([("go.mod".to_string(), self.go_mod())]);
+        for (path, pkg) in &self.files {
+            let mut pkg = pkg.clone();
+            let dirs = std::mem::take(&mut pkg.module_imports);
+            pkg.imports.extend(dirs.iter().map(|dir| self.import_path(dir)));
+            pkg.header = pkg.header.or_else(|| self.header.clone());
+            res.insert(path.clone(), pkg.to_string());
+        }
+        res
+    }
+
+    /// Write the module to `dir`, replacing it if it exists. Files go to a sibling temporary
+    /// directory first, which is then renamed to `dir`, so a failed write leaves `dir` untouched.
+    /// Hand-written regions of the existing files are kept, see [`Stmt::region`], but every
+    /// other file of `dir`, e.g. `.git` or hand-written ones, is deleted. A non-empty `dir`
+    /// without a manifest of an earlier save is refused, use [`Self::sync`] to write into it.
+    /// Every file is rewritten, [`Self::sync`] writes only those that changed.
+    pub fn save(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        crate::output::write_tree(dir.as_ref(), self.render(), "//")
+    }
+
+    /// Write only the files whose content changed to `dir`, so that tools watching it don't
+    /// rebuild everything. With `prune`, files generated by an earlier save or sync that are no
+    /// longer produced are deleted.
+    pub fn sync(&self, dir: impl AsRef<Path>, prune: bool) -> std::io::Result<SaveReport> {
+        crate::output::sync_tree(dir.as_ref(), self.render(), "//", prune)
+    }
+}
@@ -0,0 +1,98 @@
+//! Turn arbitrary names into Go identifiers. e.g. `user-id` -> `UserID` or `userID`
+
+use super::lexer::KEYWORDS;
+use crate::naming::{capitalize, words};
+
+/// Words written in all caps by Go convention.
+pub const INITIALISMS: [&str; 39] = [
+    "ACL", "API", "ASCII", "CPU", "CSS", "DNS", "EOF", "GUID", "HTML", "HTTP", "HTTPS", "ID", "IP",
+    "JSON", "LHS", "QPS", "RAM", "RHS", "RPC", "SLA", "SMTP", "SQL", "SSH", "TCP", "TLS", "TTL",
+    "UDP", "UI", "UID", "UUID", "URI", "URL", "UTF8", "VM", "XML", "XMPP", "XSRF", "XSS", "YAML",
+];
+
+/// Predeclared types, constants and functions, valid names that shadow the builtins.
+pub const PREDECLARED: [&str; 44] = [
+    "any",
+    "bool",
+    "byte",
+    "comparable",
+    "complex64",
+    "complex128",
+    "error",
+    "float32",
+    "float64",
+    "int",
+    "int8",
+    "int16",
+    "int32",
+    "int64",
+    "rune",
+    "string",
+    "uint",
+    "uint8",
+    "uint16",
+    "uint32",
+    "uint64",
+    "uintptr",
+    "true",
+    "false",
+    "iota",
+    "nil",
+    "append",
+    "cap",
+    "clear",
+    "close",
+    "complex",
+    "copy",
+    "delete",
+    "imag",
+    "len",
+    "make",
+    "max",
+    "min",
+    "new",
+    "panic",
+    "print",
+    "println",
+    "real",
+    "recover",
+];
+
+fn word(word: &str) -> String {
+    let upper = word.to_uppercase();
+    if INITIALISMS.contains(&upper.as_str()) { upper } else { capitalize(word) }
+}
+
+/// Exported (PascalCase) name, `X` is prepended if it would start with a digit.
+/// e.g. `user_id` -> `UserID`, `2fa-enabled` -> `X2faEnabled`
+pub fn exported(name: &str) -> String {
+    let name: String = words(name).iter().map(|w| word(w)).collect();
+    if name.starts_with(|c: char| c.is_alphabetic()) { name } else { format!("X{name}") }
+}
+
+/// Unexported (camelCase) name, keywords and predeclared names get a `_` suffix and `_` is
+/// prepended if it would start with a digit. e.g. `HTTPServer` -> `httpServer`, `type` -> `type_`
+pub fn unexported(name: &str) -> String {
+    let words = words(name);
+    let mut words = words.iter();
+    let first = words.next().map_or(String::new(), |w| w.to_lowercase());
+    let name = first + &words.map(|w| word(w)).collect::<String>();
+    escape(&name)
+}
+
+/// Make `name` a valid identifier, keeping its case. e.g. `user-id` -> `user_id`, `func` -> `func_`
+pub fn ident(name: &str) -> String {
+    escape(&name.chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect::<String>())
+}
+
+fn escape(name: &str) -> String {
+    if name.is_empty() {
+        "_".into()
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else if KEYWORDS.contains(&name) || PREDECLARED.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.into()
+    }
+}
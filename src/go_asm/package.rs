@@ -1,101 +1,448 @@
 use super::*;
+use crate::output::{Header, SourceMap};
+use std::collections::BTreeSet;
 
 #[derive(Clone)]
-pub struct Decl(pub String);
+pub struct Decl(pub String, pub Meta);
 
 impl Decl {
     pub fn raw(raw: impl ToString) -> Self {
-        Self(raw.to_string())
+        Self(raw.to_string(), Meta::new())
     }
 
+    /// Record that the declaration refers to package `lib`.
+    pub fn with_import(mut self, lib: impl Display) -> Self {
+        self.1.imports.insert(lib.to_string());
+        self
+    }
+
+    /// Record that the declaration refers to the package in `dir` of its [`Module`], see
+    /// [`Meta::module_import`].
+    pub fn with_module_import(mut self, dir: impl Display) -> Self {
+        self.1 = self.1.module_import(dir);
+        self
+    }
+
+    /// Pushed to a [`Package`], it's hoisted into the import block.
     pub fn import(lib: impl Display) -> Self {
-        Self(format!("import \"{lib}\""))
+        Self::raw(format!("import \"{lib}\"")).with_import(lib)
     }
 
     pub fn variable(name: impl Display, val: Expr) -> Self {
-        Self(format!("var {name} = {val}"))
+        Self(format!("var {name} = {val:#}"), val.1)
     }
 
     pub fn uninit_var(bind: Type) -> Self {
-        Self(format!("var {bind}"))
+        Self::raw(format!("var {bind}"))
+    }
+
+    /// e.g. `var Name Type = val`
+    pub fn typed_var(name: impl Display, ty: Type, val: Expr) -> Self {
+        Self(format!("var {name} {ty} = {val:#}"), val.1)
     }
 
     pub fn const_(name: impl Display, val: Expr) -> Self {
-        Self(format!("const {name} = {val}"))
+        Self(format!("const {name} = {val:#}"), val.1)
+    }
+
+    /// e.g. `const Name Type = val`
+    pub fn typed_const(name: impl Display, ty: Type, val: Expr) -> Self {
+        Self(format!("const {name} {ty} = {val:#}"), val.1)
+    }
+
+    /// e.g. `const (\n    A Type = iota\n    B\n)`
+    pub fn const_group(specs: impl IntoIterator<Item = ValueSpec>) -> Self {
+        Self::group("const", specs)
+    }
+
+    /// e.g. `var (\n    a int\n    b = "b"\n)`
+    pub fn var_group(specs: impl IntoIterator<Item = ValueSpec>) -> Self {
+        Self::group("var", specs)
+    }
+
+    /// e.g. `type (\n    A int\n    B string\n)`
+    pub fn type_group(types: impl IntoIterator<Item = (impl Display, Type)>) -> Self {
+        let types: String =
+            types.into_iter().map(|(name, ty)| format!("    {name} {ty}\n")).collect();
+        Self::raw(format!("type (\n{types})"))
+    }
+
+    fn group(kw: &str, specs: impl IntoIterator<Item = ValueSpec>) -> Self {
+        let specs = specs.into_iter().map(|s| {
+            let meta = s.val.as_ref().map(|v| v.1.clone()).unwrap_or_default();
+            Expr(format!("    {s:#}\n"), meta)
+        });
+        let specs = Expr::join(specs, "");
+        Self(format!("{kw} (\n{specs:#})"), specs.1)
     }
 
     /// e.g. `type TypeName struct{}`
     pub fn type_(name: impl Display, ty: Type) -> Self {
-        Self(format!("type {name} {ty}"))
+        Self::raw(format!("type {name} {ty}"))
     }
 
     /// e.g. `func Function(arg1 int, ...) int { ... }`
-    pub fn func(
-        name: impl Display,
-        args: impl IntoIterator<Item = Type>,
-        rets: impl IntoIterator<Item = Type>,
-        body: Block,
-    ) -> Self {
-        let args = args.into_iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
-        let mut rets = rets.into_iter().map(|r| r.to_string()).collect::<Vec<_>>().join(", ");
-        if !rets.is_empty() {
-            rets = format!(" {rets}")
+    pub fn func(name: impl Display, sig: Signature, body: Block) -> Self {
+        Self(format!("func {name}{sig} {body:#}"), body.1)
+    }
+
+    /// Record that the declaration is produced from `origin`, see [`Stmt::origin`].
+    pub fn origin(self, origin: impl Display) -> Self {
+        Self(crate::output::with_origin(origin, &self.0), self.1)
+    }
+
+    /// A top-level region kept when saving over an existing file, see [`Stmt::region`].
+    pub fn region(name: impl Display, decls: impl IntoIterator<Item = Decl>) -> Self {
+        let mut res = Self::raw("");
+        let mut body = Vec::new();
+        for decl in decls {
+            body.push(decl.0);
+            res.1.merge(decl.1);
         }
-        Self(format!("func {name}({args}){rets} {body}"))
+        res.0 = crate::output::region("//", name, &body.join("\n"));
+        res
     }
 
     /// e.g. `func (r Receiver) Function(arg1 int, ...) int { ... }`
-    pub fn method(
+    pub fn method(name: impl Display, receiver: Type, sig: Signature, body: Block) -> Self {
+        Self(format!("func ({receiver}) {name}{sig} {body:#}"), body.1)
+    }
+}
+
+impl Display for Decl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        crate::output::fmt_node(f, &self.0)
+    }
+}
+
+/// One line of a `const (...)` or `var (...)` group. e.g. `Red Color = iota`
+#[derive(Clone)]
+pub struct ValueSpec {
+    pub name: String,
+    pub ty: Option<Type>,
+    pub val: Option<Expr>,
+}
+
+impl ValueSpec {
+    pub fn new(name: impl Display, ty: Option<Type>, val: Option<Expr>) -> Self {
+        Self { name: name.to_string(), ty, val }
+    }
+}
+
+impl Display for ValueSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(ty) = &self.ty {
+            write!(f, " {ty}")?;
+        }
+        if let Some(val) = &self.val {
+            // forward `{:#}`, keeping the origins of `val` inside a group
+            write!(f, " = ")?;
+            val.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// An `iota` based enum. e.g.
+/// ```go
+/// type Color int
+/// const (
+///     Red Color = iota
+///     Green
+/// )
+/// func (c Color) String() string { ... }
+/// ```
+#[derive(Clone)]
+pub struct Enum {
+    pub name: String,
+    pub base: Type,
+    /// Constant name and its text.
+    pub variants: Vec<(String, String)>,
+    /// Generate `MarshalText` and `UnmarshalText`.
+    pub text: bool,
+}
+
+impl Enum {
+    pub fn new(name: impl Display, base: Type) -> Self {
+        Self { name: name.to_string(), base, variants: Vec::new(), text: false }
+    }
+
+    /// Add a variant whose text is its name.
+    pub fn variant(self, name: impl Display) -> Self {
+        let name = name.to_string();
+        self.variant_as(name.clone(), name)
+    }
+
+    /// Add a variant with a custom text. e.g. `variant_as("ColorRed", "red")`
+    pub fn variant_as(mut self, name: impl Display, text: impl Display) -> Self {
+        self.variants.push((name.to_string(), text.to_string()));
+        self
+    }
+
+    /// Also implement `encoding.TextMarshaler` and `encoding.TextUnmarshaler`.
+    pub fn text_marshaling(self) -> Self {
+        Self { text: true, ..self }
+    }
+}
+
+impl From<Enum> for Decl {
+    fn from(e: Enum) -> Self {
+        let name = e.name;
+        let ty = Type::raw(&name);
+        let consts = e.variants.iter().enumerate().map(|(i, (variant, _))| match i {
+            0 => ValueSpec::new(variant, Some(ty.clone()), Some(Expr::raw("iota"))),
+            _ => ValueSpec::new(variant, None, None),
+        });
+        let consts = Decl::const_group(consts);
+
+        let recv =
+            Expr::raw(name.chars().next().map_or(String::new(), |c| c.to_lowercase().collect()));
+        let cases = e.variants.iter().map(|(variant, text)| {
+            (Expr::raw(variant), Block::new().push(Stmt::ret([text.as_str().into()])))
+        });
+        let fallback = Expr::raw("fmt.Sprintf")
+            .call([format!("{name}(%d)").into(), Expr::raw(&e.base).call([recv.clone()])]);
+        let body = Block::new()
+            .push(Stmt::switch(recv.clone(), cases, Block::new()))
+            .push(Stmt::ret([fallback]));
+        let string_sig = Signature::new().result("", Type::string());
+        let mut td = TypeDecl::new(&name, e.base)
+            .method("String", string_sig, body)
+            .expect("`String` is the first method");
+
+        if e.text {
+            let bytes = Type::raw("byte").slice();
+            let err = Type::raw("error");
+            let body = Block::new().push(Stmt::ret([
+                Expr::raw("[]byte").call([recv.clone().attr("String").call([])]),
+                Expr::raw("nil"),
+            ]));
+            let sig = Signature::new().result("", bytes.clone()).result("", err.clone());
+            td = td.method("MarshalText", sig, body).expect("methods are unique");
+
+            let text = Expr::raw("text");
+            let cases = e.variants.iter().map(|(variant, text)| {
+                let assign = Stmt::raw(format!("*{recv} = {variant:#}"));
+                (
+                    text.as_str().into(),
+                    Block::new().push(assign).push(Stmt::ret([Expr::raw("nil")])),
+                )
+            });
+            let invalid =
+                Expr::raw("fmt.Errorf").call([format!("invalid {name}: %q").into(), text.clone()]);
+            let body = Block::new()
+                .push(Stmt::switch(Expr::raw("string").call([text]), cases, Block::new()))
+                .push(Stmt::ret([invalid]));
+            let sig = Signature::new().param("text", bytes).result("", err);
+            td = td.ptr_method("UnmarshalText", sig, body).expect("methods are unique");
+        }
+
+        let Decl(td, mut meta) = td.into();
+        meta.merge(consts.1.clone());
+        let (type_, methods) = td.split_once('\n').unwrap_or((&td, ""));
+        Decl(format!("{type_}\n{consts:#}\n{methods}"), meta).with_import("fmt")
+    }
+}
+
+/// A method of a [`TypeDecl`].
+#[derive(Clone)]
+pub struct Method {
+    pub name: String,
+    /// Whether the receiver is `*T` rather than `T`.
+    pub ptr: bool,
+    pub sig: Signature,
+    pub body: Block,
+}
+
+/// A named type together with its constructor and methods.
+///
+/// e.g.
+/// ```go
+/// type Point struct { ... }
+/// func NewPoint(...) *Point { ... }
+/// func (p *Point) Move(...) { ... }
+/// ```
+#[derive(Clone)]
+pub struct TypeDecl {
+    pub name: String,
+    pub ty: Type,
+    /// Receiver name shared by every method.
+    pub receiver: String,
+    pub constructor: Option<(Signature, Block)>,
+    pub methods: Vec<Method>,
+}
+
+impl TypeDecl {
+    /// The receiver is named after the lowercased first letter of `name`.
+    pub fn new(name: impl Display, ty: Type) -> Self {
+        let name = name.to_string();
+        let receiver = name.chars().next().map_or(String::new(), |c| c.to_lowercase().collect());
+        Self { name, ty, receiver, constructor: None, methods: Vec::new() }
+    }
+
+    pub fn receiver(self, receiver: impl Display) -> Self {
+        Self { receiver: receiver.to_string(), ..self }
+    }
+
+    /// e.g. `func NewPoint(x, y int) *Point { ... }`
+    pub fn constructor(self, sig: Signature, body: Block) -> Self {
+        Self { constructor: Some((sig, body)), ..self }
+    }
+
+    /// Add a method with a value receiver.
+    pub fn method(self, name: impl Display, sig: Signature, body: Block) -> Result<Self, Error> {
+        self.push_method(name, false, sig, body)
+    }
+
+    /// Add a method with a pointer receiver.
+    pub fn ptr_method(
+        self,
         name: impl Display,
-        receiver: Type,
-        args: impl IntoIterator<Item = Type>,
-        rets: impl IntoIterator<Item = Type>,
+        sig: Signature,
         body: Block,
-    ) -> Self {
-        let args = args.into_iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
-        let mut rets = rets.into_iter().map(|r| r.to_string()).collect::<Vec<_>>().join(", ");
-        if !rets.is_empty() {
-            rets = format!(" {rets}")
+    ) -> Result<Self, Error> {
+        self.push_method(name, true, sig, body)
+    }
+
+    fn push_method(
+        mut self,
+        name: impl Display,
+        ptr: bool,
+        sig: Signature,
+        body: Block,
+    ) -> Result<Self, Error> {
+        let name = name.to_string();
+        if self.methods.iter().any(|m| m.name == name) {
+            return Err(Error::DuplicateMethod(name));
         }
-        Self(format!("func ({receiver}) {name}({args}){rets} {body}"))
+        self.methods.push(Method { name, ptr, sig, body });
+        Ok(self)
+    }
+
+    /// Check that the method set of the type (or of `*T` if `ptr`) contains `methods`, the same
+    /// list that would be given to [`Type::interface`].
+    pub fn satisfies(&self, methods: &[IfaceFn], ptr: bool) -> Result<(), Error> {
+        for want in methods {
+            let Some(have) = self.methods.iter().find(|m| m.name == want.name) else {
+                return Err(Error::MissingMethod(want.name.clone()));
+            };
+            if !have.sig.same_types(&want.sig) {
+                return Err(Error::MethodMismatch(want.name.clone()));
+            }
+            if have.ptr && !ptr {
+                return Err(Error::PointerReceiver(want.name.clone()));
+            }
+        }
+        Ok(())
     }
 }
 
-impl Display for Decl {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+impl From<TypeDecl> for Decl {
+    fn from(td: TypeDecl) -> Self {
+        let mut res = Decl::raw(format!("type {} {}", td.name, td.ty));
+        if let Some((sig, body)) = td.constructor {
+            write!(res.0, "\nfunc New{}{sig} {body:#}", td.name).unwrap();
+            res.1.merge(body.1);
+        }
+        for m in td.methods {
+            let ptr = if m.ptr { "*" } else { "" };
+            write!(
+                res.0,
+                "\nfunc ({} {ptr}{}) {}{} {:#}",
+                td.receiver, td.name, m.name, m.sig, m.body
+            )
+            .unwrap();
+            res.1.merge(m.body.1);
+        }
+        res
     }
 }
 
 #[derive(Clone)]
 pub struct Package {
     pub name: Box<str>,
+    /// Rendered before the package clause, marks the file as generated.
+    pub header: Option<Header>,
+    /// Imports of all pushed declarations, rendered after the package clause.
+    pub imports: BTreeSet<String>,
+    /// Module imports of all pushed declarations, see [`Meta::module_import`]. Only rendered in
+    /// a [`Module`], [`Package::validate`] reports them otherwise.
+    pub module_imports: BTreeSet<String>,
     pub decls: String,
 }
 
 impl Package {
     pub fn new(name: impl Into<Box<str>>) -> Self {
-        Self { name: name.into(), decls: String::new() }
+        Self {
+            name: name.into(),
+            header: None,
+            imports: BTreeSet::new(),
+            module_imports: BTreeSet::new(),
+            decls: String::new(),
+        }
+    }
+
+    /// e.g. `// Code generated by sdkgen v1.4.0. DO NOT EDIT.` above the package clause.
+    pub fn header(self, header: Header) -> Self {
+        Self { header: Some(header), ..self }
     }
 
-    pub fn push(self, decl: Decl) -> Self {
-        Self { name: self.name, decls: format!("{}{decl}\n", self.decls) }
+    pub fn push(mut self, decl: Decl) -> Self {
+        self.pushs(decl);
+        self
     }
 
     /// push decl to `&mut Package`
     pub fn pushs(&mut self, decl: Decl) -> &mut Self {
-        writeln!(self.decls, "{decl}").unwrap();
+        let code = crate::output::strip_origins(&decl.0);
+        let hoisted = decl.1.imports.iter().any(|lib| code == format!("import \"{lib}\""));
+        if !hoisted {
+            writeln!(self.decls, "{decl:#}").unwrap();
+        }
+        self.imports.extend(decl.1.imports);
+        self.module_imports.extend(decl.1.module_imports);
         self
     }
 
-    /// save defined package to file.
+    /// save defined package to file, left untouched if it already has the same content. The
+    /// hand-written regions of an existing file are kept, see [`Stmt::region`].
     pub fn save(&self, path: &str) -> std::io::Result<()> {
-        std::fs::write(path, self.to_string())
+        crate::output::save_file(path.as_ref(), self.to_string(), "//").map(|_| ())
+    }
+
+    /// The rendered package and the map of its lines to the origins of their nodes, see
+    /// [`Stmt::origin`]. `file` is the name of the generated file in the map.
+    pub fn source_map(&self, file: &str) -> (String, SourceMap) {
+        crate::output::source_map(file, &self.source())
+    }
+
+    /// [`Package::save`], with the source map next to the file as `<path>.map`.
+    pub fn save_with_source_map(&self, path: &str) -> std::io::Result<()> {
+        crate::output::save_with_map(path.as_ref(), self.source(), "//").map(|_| ())
+    }
+
+    /// The rendered package, with the origin markers of its nodes.
+    fn source(&self) -> String {
+        let mut res = self.header.as_ref().map(|h| h.render("//")).unwrap_or_default();
+        writeln!(res, "package {}", self.name).unwrap();
+        match self.imports.len() {
+            0 => {}
+            1 => writeln!(res, "import \"{}\"", self.imports.first().unwrap()).unwrap(),
+            _ => {
+                res.push_str("import (\n");
+                for lib in &self.imports {
+                    writeln!(res, "    \"{lib}\"").unwrap();
+                }
+                res.push_str(")\n");
+            }
+        }
+        res + &self.decls
     }
 }
 
 impl Display for Package {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "package {}\n{}", self.name, self.decls)
+        write!(f, "{}", crate::output::strip_origins(&self.source()))
     }
 }
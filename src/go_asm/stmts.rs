@@ -1,145 +1,503 @@
 use super::*;
 
 #[derive(Clone, Default)]
-pub struct Block(pub String);
+pub struct Block(pub String, pub Meta);
 
 impl Block {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn push(self, stmt: Stmt) -> Self {
-        Self(format!("{}    {stmt}\n", self.0))
+    pub fn push(mut self, stmt: Stmt) -> Self {
+        self.1.merge(stmt.1);
+        Self(format!("{}    {}\n", self.0, stmt.0), self.1)
     }
 
     /// push stmt to `&mut Block`
     pub fn pushs(&mut self, stmt: Stmt) -> &mut Self {
-        writeln!(self.0, "{stmt}").unwrap();
+        writeln!(self.0, "{}", stmt.0).unwrap();
+        self.1.merge(stmt.1);
         self
     }
 }
 
 impl Display for Block {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{{\n{}}}", self.0)
+        f.write_str("{\n")?;
+        crate::output::fmt_node(f, &self.0)?;
+        f.write_str("}")
     }
 }
 
 #[derive(Clone)]
-pub struct Stmt(pub String);
+pub struct Stmt(pub String, pub Meta);
 
 impl Stmt {
     pub fn raw(raw: impl ToString) -> Self {
-        Self(raw.to_string())
+        Self(raw.to_string(), Meta::new())
+    }
+
+    /// Record that the statement refers to package `lib`.
+    pub fn with_import(mut self, lib: impl Display) -> Self {
+        self.1.imports.insert(lib.to_string());
+        self
+    }
+
+    /// Record that the statement refers to the package in `dir` of its [`Module`], see
+    /// [`Meta::module_import`].
+    pub fn with_module_import(mut self, dir: impl Display) -> Self {
+        self.1 = self.1.module_import(dir);
+        self
+    }
+
+    /// Record names declared in the enclosing scope.
+    fn binds(mut self, names: impl IntoIterator<Item = impl Display>) -> Self {
+        for name in names {
+            self.1 = self.1.bind(name);
+        }
+        self
+    }
+
+    /// Record that the statement is produced from `origin`, e.g. `schema.json#/definitions/User`,
+    /// for [`Package::source_map`].
+    pub fn origin(self, origin: impl Display) -> Self {
+        Self(crate::output::with_origin(origin, &self.0), self.1)
+    }
+
+    /// Merge the meta of nested nodes.
+    fn with_meta(mut self, metas: impl IntoIterator<Item = Meta>) -> Self {
+        for meta in metas {
+            self.1.merge(meta);
+        }
+        self
     }
 
     /// break
     pub fn brk() -> Self {
-        Self("break".into())
+        Self::raw("break")
     }
 
     /// continue
     pub fn cont() -> Self {
-        Self("continue".into())
+        Self::raw("continue")
     }
 
     /// fallthrough
     pub fn fall() -> Self {
-        Self("fallthrough".into())
+        Self::raw("fallthrough")
+    }
+
+    /// break Label
+    pub fn brk_label(label: impl Display) -> Self {
+        Self::raw(format!("break {label}"))
+    }
+
+    /// continue Label
+    pub fn cont_label(label: impl Display) -> Self {
+        Self::raw(format!("continue {label}"))
+    }
+
+    /// goto Label
+    pub fn goto(label: impl Display) -> Self {
+        Self::raw(format!("goto {label}"))
+    }
+
+    /// e.g. `Outer: for { ... }`
+    pub fn label(label: impl Display, stmt: Stmt) -> Self {
+        Self(format!("{label}: {}", stmt.0), stmt.1)
     }
 
     pub fn defer(call: Expr) -> Self {
-        Self(format!("defer {call}"))
+        Self(format!("defer {call:#}"), call.1)
+    }
+
+    /// e.g. `go worker(ch)`
+    pub fn go(call: Expr) -> Self {
+        Self(format!("go {call:#}"), call.1)
+    }
+
+    /// e.g. `ch <- v`
+    pub fn send(ch: Expr, val: Expr) -> Self {
+        Self::raw(format!("{ch:#} <- {val:#}")).with_meta([ch.1, val.1])
+    }
+
+    /// x++
+    pub fn inc(expr: Expr) -> Self {
+        Self(format!("{expr:#}++"), expr.1)
+    }
+
+    /// x--
+    pub fn dec(expr: Expr) -> Self {
+        Self(format!("{expr:#}--"), expr.1)
+    }
+
+    /// e.g. `x = 1`
+    pub fn assign(lhs: Expr, rhs: Expr) -> Self {
+        Self::raw(format!("{lhs:#} = {rhs:#}")).with_meta([lhs.1, rhs.1])
+    }
+
+    /// e.g. `a, b = b, a`
+    pub fn assigns(
+        lhs: impl IntoIterator<Item = Expr>,
+        rhs: impl IntoIterator<Item = Expr>,
+    ) -> Self {
+        let (lhs, rhs) = (Expr::join(lhs, ", "), Expr::join(rhs, ", "));
+        Self::raw(format!("{lhs:#} = {rhs:#}")).with_meta([lhs.1, rhs.1])
+    }
+
+    /// Compound assignment, `op` is the binary operator. e.g. `op_assign(x, "<<", 1)` -> `x <<= 1`
+    pub fn op_assign(lhs: Expr, op: &str, rhs: Expr) -> Self {
+        Self::raw(format!("{lhs:#} {op}= {rhs:#}")).with_meta([lhs.1, rhs.1])
     }
 
     pub fn ret(vals: impl IntoIterator<Item = Expr>) -> Self {
-        let vals = vals.into_iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ");
-        Self(format!("return {vals}"))
+        let vals = Expr::join(vals, ", ");
+        Self(format!("return {vals:#}"), vals.1)
     }
 
     pub fn variable(name: impl Display, val: Expr) -> Self {
-        Self(format!("{name} := {val}"))
+        Self(format!("{name} := {val:#}"), val.1).binds([name])
     }
 
     pub fn uninit_var(name: impl Display, ty: Type) -> Self {
-        Self(format!("var {name} {ty}"))
+        Self::raw(format!("var {name} {ty}")).binds([name])
     }
 
     pub fn variables(
         names: impl IntoIterator<Item = impl Display>,
         vals: impl IntoIterator<Item = Expr>,
     ) -> Self {
-        let names = names.into_iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
-        let vals = vals.into_iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
-        Self(format!("{names} := {vals}"))
+        let names = names.into_iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        let vals = Expr::join(vals, ", ");
+        Self(format!("{} := {vals:#}", names.join(", ")), vals.1).binds(names)
     }
 
     pub fn const_(name: impl Display, val: Expr) -> Self {
-        Self(format!("const {name} = {val}"))
+        Self(format!("const {name} = {val:#}"), val.1).binds([name])
+    }
+
+    /// A region whose statements are hand-written, kept when saving over an existing file.
+    /// `stmts` are the default content. e.g.
+    /// ```go
+    /// // codeasm:begin validate
+    /// // codeasm:end
+    /// ```
+    pub fn region(name: impl Display, stmts: impl IntoIterator<Item = Stmt>) -> Self {
+        let mut res = Self::raw("");
+        let mut body = Vec::new();
+        for stmt in stmts {
+            body.push(stmt.0);
+            res.1.merge(stmt.1);
+        }
+        res.0 = crate::output::region("//", name, &body.join("\n"));
+        res
     }
 
     pub fn block(stmts: impl IntoIterator<Item = Stmt>) -> Self {
-        Self(format!(
-            "{{\n{}}}",
-            stmts.into_iter().map(|s| format!("    {s}\n")).collect::<String>()
-        ))
+        let block = stmts.into_iter().fold(Block::new(), Block::push);
+        Self(format!("{block:#}"), block.1)
     }
 
     pub fn if_(cases: impl IntoIterator<Item = (Expr, Block)>, else_: Block) -> Self {
-        let mut res = String::new();
+        let mut res = Self::raw("");
         let mut cases = cases.into_iter();
         let (cond, block) = cases.next().expect("`cases` is empty");
-        write!(res, "if {cond} {block}").unwrap();
+        write!(res.0, "if {cond:#} {block:#}").unwrap();
+        res.1.merge(cond.1).merge(block.1);
         for (cond, block) in cases {
-            write!(res, " else if {cond} {block}").unwrap();
+            write!(res.0, " else if {cond:#} {block:#}").unwrap();
+            res.1.merge(cond.1).merge(block.1);
         }
         if !else_.0.is_empty() {
-            write!(res, " else {else_}").unwrap();
+            write!(res.0, " else {else_:#}").unwrap();
+            res.1.merge(else_.1);
         }
-        Self(res)
+        res
     }
 
+    /// e.g. `switch x { case 1: ... default: ... }`, see [`Switch`] for other forms.
     pub fn switch(
         val: Expr,
         cases: impl IntoIterator<Item = (Expr, Block)>,
         default: Block,
     ) -> Self {
-        let mut res = format!("switch {val} {{");
+        let mut switch = Switch::on(val);
         for (v, block) in cases {
-            write!(res, "case {v}:\n{}", block.0).unwrap();
+            switch = switch.case([v], block);
         }
         if !default.0.is_empty() {
-            write!(res, "default:\n{}", default.0).unwrap();
+            switch = switch.default(default);
         }
-        Self(format!("{res}}}"))
+        switch.build().expect("no init statement or fallthrough")
+    }
+
+    /// `cases` are communications, e.g. `Stmt::send(ch, v)` or `Stmt::variable("v", ch.recv())`.
+    /// An empty `default` block makes a non-blocking select.
+    pub fn select(cases: impl IntoIterator<Item = (Stmt, Block)>, default: Option<Block>) -> Self {
+        let mut res = Self::raw("select {\n");
+        for (comm, block) in cases {
+            write!(res.0, "case {}:\n{}", comm.0, block.0).unwrap();
+            res.1.merge(comm.1).merge(block.1);
+        }
+        if let Some(default) = default {
+            write!(res.0, "default:\n{}", default.0).unwrap();
+            res.1.merge(default.1);
+        }
+        res.0.push('}');
+        res
     }
 
     /// e.g. `for i := 0; i <= 10; i++ { sum += i }`
-    pub fn for_(init: Option<Stmt>, cond: Option<Expr>, post: Option<Stmt>, body: Block) -> Self {
-        let has_init = init.is_some();
-        let has_cond = cond.is_some();
-        let has_post = post.is_some();
-
-        match (has_init, has_cond, has_post) {
-            (true, true, true) => {
-                Self(format!("for {}; {}; {} {body}", init.unwrap(), cond.unwrap(), post.unwrap()))
+    ///
+    /// Any clause may be omitted. `init` and `post` must be simple statements and `post` can't
+    /// declare variables.
+    pub fn for_(
+        init: Option<Stmt>,
+        cond: Option<Expr>,
+        post: Option<Stmt>,
+        body: Block,
+    ) -> Result<Self, Error> {
+        if let Some(init) = init.as_ref().filter(|s| !s.is_simple(true)) {
+            return Err(Error::InvalidForInit(init.to_string()));
+        }
+        if let Some(post) = post.as_ref().filter(|s| !s.is_simple(false)) {
+            return Err(Error::InvalidForPost(post.to_string()));
+        }
+        let metas = [init.as_ref(), post.as_ref()].into_iter().flatten().map(|s| s.1.clone());
+        let metas: Vec<_> =
+            metas.chain(cond.as_ref().map(|c| c.1.clone())).chain([body.1.clone()]).collect();
+        let res = match (init, cond, post) {
+            (None, None, None) => Self::raw(format!("for {body:#}")),
+            (None, Some(cond), None) => Self::raw(format!("for {cond:#} {body:#}")),
+            (init, cond, post) => {
+                let [init, cond, post] = [init.map(|s| s.0), cond.map(|c| c.0), post.map(|s| s.0)]
+                    .map(Option::unwrap_or_default);
+                Self::raw(format!("for {} {body:#}", format!("{init}; {cond}; {post}").trim()))
+            }
+        };
+        Ok(res.with_meta(metas))
+    }
+
+    /// Whether this is a simple statement, i.e. one allowed in `if`, `switch` and `for` clauses.
+    fn is_simple(&self, allow_define: bool) -> bool {
+        const KEYWORDS: &str =
+            "var const type return break continue goto fallthrough if for switch select go defer";
+        // origins may contain anything, e.g. `api.yaml:12:3`.
+        let code = crate::output::strip_origins(&self.0);
+        let first = code.split(|c: char| !c.is_alphanumeric() && c != '_').next().unwrap_or("");
+        if KEYWORDS.split(' ').any(|k| k == first) || code.starts_with('{') {
+            return false;
+        }
+        let (mut depth, mut quote) = (0, None);
+        let mut chars = code.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match (quote, c) {
+                // a backslash is literal in a raw string.
+                (Some('"' | '\''), '\\') => _ = chars.next(),
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '"' | '\'' | '`') => quote = Some(c),
+                (None, '(' | '[' | '{') => depth += 1,
+                (None, ')' | ']' | '}') => depth -= 1,
+                // labeled statement, or a short variable declaration.
+                (None, ':') if depth == 0 && (!allow_define || !code[i..].starts_with(":=")) => {
+                    return false;
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+/// A clause of a [`Switch`], an empty `values` is the `default` clause.
+#[derive(Clone)]
+pub struct Case {
+    pub values: Vec<Expr>,
+    pub body: Block,
+    pub fallthrough: bool,
+}
+
+/// A switch statement. e.g.
+/// ```go
+/// switch v := x.(type) {
+/// case int, string:
+///     ...
+/// case nil:
+///     ...
+/// default:
+///     ...
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct Switch {
+    pub init: Option<Stmt>,
+    /// The switch expression, e.g. `x` or `v := x.(type)`. `None` for a tagless switch.
+    pub tag: Option<Expr>,
+    pub is_type_switch: bool,
+    pub cases: Vec<Case>,
+    pub default: Option<Case>,
+}
+
+impl Switch {
+    /// Tagless switch. e.g. `switch { case x > 0: ... }`
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// e.g. `switch x { ... }`
+    pub fn on(tag: Expr) -> Self {
+        Self { tag: Some(tag), ..Default::default() }
+    }
+
+    /// Type switch. e.g. `switch v := x.(type) { ... }`, `bind` may be empty.
+    pub fn on_type(bind: impl Display, x: Expr) -> Self {
+        let bind = bind.to_string();
+        let tag = if bind.is_empty() {
+            x.type_sw()
+        } else {
+            let sw = x.type_sw();
+            Expr(format!("{bind} := {sw:#}"), sw.1)
+        };
+        Self { tag: Some(tag), is_type_switch: true, ..Default::default() }
+    }
+
+    /// e.g. `switch x := f(); x { ... }`
+    pub fn init(self, init: Stmt) -> Self {
+        Self { init: Some(init), ..self }
+    }
+
+    /// Add a clause. Values are types for a type switch, e.g. `Type::int(64).into()`, there must
+    /// be at least one, see [`Self::default`] for a clause without.
+    pub fn case(mut self, values: impl IntoIterator<Item = Expr>, body: Block) -> Self {
+        self.cases.push(Case { values: values.into_iter().collect(), body, fallthrough: false });
+        self
+    }
+
+    pub fn default(self, body: Block) -> Self {
+        Self { default: Some(Case { values: Vec::new(), body, fallthrough: false }), ..self }
+    }
+
+    /// End the last added clause with `fallthrough`.
+    pub fn fallthrough(mut self) -> Self {
+        if let Some(case) = self.cases.last_mut() {
+            case.fallthrough = true;
+        }
+        self
+    }
+
+    pub fn build(self) -> Result<Stmt, Error> {
+        let mut res = Stmt::raw("switch ");
+        if let Some(init) = self.init {
+            if !init.is_simple(true) {
+                return Err(Error::InvalidSwitchInit(init.to_string()));
             }
-            (true, false, false) => Self(format!("for {} {body}", init.unwrap())),
-            (false, true, false) => Self(format!("for {} {body}", cond.unwrap())),
-            (false, false, false) => Self(format!("for {body}")),
-            _ => panic!("invalid for loop"),
+            write!(res.0, "{}; ", init.0).unwrap();
+            res.1.merge(init.1);
         }
+        if let Some(tag) = self.tag {
+            write!(res.0, "{tag:#} ").unwrap();
+            res.1.merge(tag.1);
+        }
+        res.0.push_str("{\n");
+        let cases = self.cases.len();
+        let clauses: Vec<_> = self.cases.into_iter().chain(self.default).collect();
+        let len = clauses.len();
+        for (i, case) in clauses.into_iter().enumerate() {
+            if case.fallthrough && self.is_type_switch {
+                return Err(Error::FallthroughInTypeSwitch);
+            }
+            if case.fallthrough && i + 1 == len {
+                return Err(Error::FallthroughInLastCase);
+            }
+            if i == cases {
+                res.0.push_str("default:\n");
+            } else if case.values.is_empty() {
+                return Err(Error::EmptyCase);
+            } else {
+                let values = Expr::join(case.values, ", ");
+                writeln!(res.0, "case {values:#}:").unwrap();
+                res.1.merge(values.1);
+            }
+            res.0.push_str(&case.body.0);
+            res.1.merge(case.body.1);
+            if case.fallthrough {
+                res.0.push_str("    fallthrough\n");
+            }
+        }
+        res.0.push('}');
+        Ok(res)
+    }
+}
+
+/// A `for range` loop. e.g. `for k, v := range m { ... }`
+///
+/// Ranges over anything Go accepts: slices, maps, channels, integers (Go 1.22) and iterator
+/// functions (Go 1.23).
+#[derive(Clone)]
+pub struct Range {
+    pub key: String,
+    pub value: String,
+    /// Assign to existing variables with `=` instead of declaring them with `:=`.
+    pub assign: bool,
+    pub iter: Expr,
+}
+
+impl Range {
+    /// e.g. `Range::over(10.into())` -> `for range 10`
+    pub fn over(iter: Expr) -> Self {
+        Self { key: String::new(), value: String::new(), assign: false, iter }
+    }
+
+    pub fn key(self, key: impl Display) -> Self {
+        Self { key: key.to_string(), ..self }
+    }
+
+    /// Bind the value, the key becomes `_` if unset.
+    pub fn value(self, value: impl Display) -> Self {
+        Self { value: value.to_string(), ..self }
+    }
+
+    pub fn assign(self) -> Self {
+        Self { assign: true, ..self }
+    }
+
+    pub fn body(self, body: Block) -> Stmt {
+        let op = if self.assign { "=" } else { ":=" };
+        let vars = match (self.key.is_empty(), self.value.is_empty()) {
+            (true, true) => String::new(),
+            (false, true) => format!("{} {op} ", self.key),
+            (true, false) => format!("_, {} {op} ", self.value),
+            (false, false) => format!("{}, {} {op} ", self.key, self.value),
+        };
+        Stmt(format!("for {vars}range {:#} {body:#}", self.iter), body.1).with_meta([self.iter.1])
+    }
+}
+
+/// Loop variables, bound in the scope of the body.
+impl Bindings for Range {
+    fn bindings(&self) -> Vec<String> {
+        if self.assign {
+            return Vec::new();
+        }
+        [&self.key, &self.value].into_iter().filter(|v| !v.is_empty()).cloned().collect()
+    }
+}
+
+impl Bindings for Stmt {
+    fn bindings(&self) -> Vec<String> {
+        self.1.bindings.iter().cloned().collect()
     }
 }
 
-impl Into<Stmt> for Expr {
-    fn into(self) -> Stmt {
-        Stmt::raw(self)
+impl From<Expr> for Stmt {
+    fn from(value: Expr) -> Self {
+        Stmt(value.0, value.1)
     }
 }
 
 impl Display for Stmt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        crate::output::fmt_node(f, &self.0)
     }
 }
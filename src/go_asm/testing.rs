@@ -0,0 +1,160 @@
+use super::*;
+
+/// Declarations of `_test.go` files, they record the `testing` import.
+impl Decl {
+    /// e.g. `Decl::test("Parse", body)` -> `func TestParse(t *testing.T) { ... }`
+    pub fn test(name: impl Display, body: Block) -> Self {
+        let sig = Signature::new().param("t", Type::raw("*testing.T"));
+        Self::func(format!("Test{name}"), sig, body).with_import("testing")
+    }
+
+    /// Run `body` in a `for b.Loop()` loop after `setup`, which isn't timed. Needs Go 1.24.
+    /// e.g. `func BenchmarkParse(b *testing.B) { ...; for b.Loop() { ... } }`
+    pub fn benchmark(name: impl Display, setup: Block, body: Block) -> Self {
+        Self::bench(name, setup, format!("for b.Loop() {body:#}"), body.1)
+    }
+
+    /// Like [`Decl::benchmark`], with a `for i := 0; i < b.N; i++` loop and `b.ResetTimer()`
+    /// after `setup`.
+    pub fn benchmark_n(name: impl Display, setup: Block, body: Block) -> Self {
+        let setup = setup.push(Stmt::raw("b.ResetTimer()"));
+        Self::bench(name, setup, format!("for i := 0; i < b.N; i++ {body:#}"), body.1)
+    }
+
+    fn bench(name: impl Display, setup: Block, loop_: String, meta: Meta) -> Self {
+        let body = setup.push(Stmt(loop_, meta));
+        let sig = Signature::new().param("b", Type::raw("*testing.B"));
+        Self::func(format!("Benchmark{name}"), sig, body).with_import("testing")
+    }
+
+    /// A fuzz target seeded with `f.Add(...)` for each seed, the fuzzed values are `params`.
+    /// e.g.
+    /// ```go
+    /// func FuzzParse(f *testing.F) {
+    ///     f.Add("1")
+    ///     f.Fuzz(func(t *testing.T, s string) { ... })
+    /// }
+    /// ```
+    pub fn fuzz(
+        name: impl Display,
+        seeds: impl IntoIterator<Item = impl IntoIterator<Item = Expr>>,
+        params: impl IntoIterator<Item = (impl Display, Type)>,
+        body: Block,
+    ) -> Self {
+        let mut block = Block::new();
+        for seed in seeds {
+            block = block.push(Expr::raw("f.Add").call(seed).into());
+        }
+        let sig = Signature::new().param("t", Type::raw("*testing.T")).params(params);
+        block = block.push(Expr::raw("f.Fuzz").call([Expr::closure(sig, body)]).into());
+        let sig = Signature::new().param("f", Type::raw("*testing.F"));
+        Self::func(format!("Fuzz{name}"), sig, block).with_import("testing")
+    }
+
+    /// An example checked against `output` by `go test`, no `// Output:` comment if it is empty.
+    /// `name` is empty, a type or function, or `Type_Method`, optionally followed by `_suffix`.
+    /// e.g. `Decl::example("Parse", body, "42")` -> `func ExampleParse() { ... }` ending in
+    /// `// Output:` and `// 42`
+    pub fn example(name: impl Display, mut body: Block, output: &str) -> Self {
+        if !output.is_empty() {
+            body.0.push_str("    // Output:\n");
+            for line in output.lines() {
+                let line = format!("    // {line}");
+                writeln!(body.0, "{}", line.trim_end()).unwrap();
+            }
+        }
+        Self::func(format!("Example{name}"), Signature::new(), body)
+    }
+}
+
+impl Package {
+    /// An external test package of the package imported as `path`, which it only sees through
+    /// the exported API. Named after the last element of `path`, e.g. `calc_test` importing
+    /// `example.com/calc`.
+    pub fn external_test(path: impl Display) -> Self {
+        let path = path.to_string();
+        let name = path.rsplit('/').next().unwrap_or_default();
+        let mut res = Self::new(format!("{name}_test"));
+        res.imports.insert(path);
+        res
+    }
+}
+
+/// A table-driven test. e.g.
+/// ```go
+/// func TestParse(t *testing.T) {
+///     tests := []struct {
+///         name string
+///         in   string
+///         want int
+///     }{
+///         {name: "one", in: "1", want: 1},
+///     }
+///     for _, tt := range tests {
+///         t.Run(tt.name, func(t *testing.T) { ... })
+///     }
+/// }
+/// ```
+/// The body of each subtest refers to the case as `tt`.
+#[derive(Clone)]
+pub struct TableTest {
+    pub name: String,
+    /// Fields of a case, after the implicit `name string`, none of them can be named `name`.
+    pub fields: Vec<Field>,
+    /// Case name and one value per field.
+    pub cases: Vec<(String, Vec<Expr>)>,
+    pub body: Block,
+}
+
+impl TableTest {
+    pub fn new(name: impl Display, fields: impl IntoIterator<Item = impl Into<Field>>) -> Self {
+        let fields = fields.into_iter().map(Into::into).collect();
+        Self { name: name.to_string(), fields, cases: Vec::new(), body: Block::new() }
+    }
+
+    pub fn case(
+        mut self,
+        name: impl Display,
+        vals: impl IntoIterator<Item = Expr>,
+    ) -> Result<Self, Error> {
+        let name = name.to_string();
+        let vals: Vec<_> = vals.into_iter().collect();
+        if vals.len() != self.fields.len() {
+            return Err(Error::TestCaseValues(name));
+        }
+        self.cases.push((name, vals));
+        Ok(self)
+    }
+
+    /// Body of the subtest run for each case.
+    pub fn body(self, body: Block) -> Self {
+        Self { body, ..self }
+    }
+}
+
+/// The test function, an error if a field is named `name`, which holds the case name.
+impl TryFrom<TableTest> for Decl {
+    type Error = Error;
+
+    fn try_from(tt: TableTest) -> Result<Self, Error> {
+        if tt.fields.iter().any(|f| f.name == "name") {
+            return Err(Error::TestCaseName);
+        }
+        let fields = [Field::new("name", Type::string())].into_iter().chain(tt.fields.clone());
+        let ty = Type::struct_([""; 0], fields).slice();
+        let cases = tt.cases.into_iter().map(|(name, vals)| {
+            let names = ["name".to_string()].into_iter();
+            let names = names.chain(tt.fields.iter().map(|f| f.name.clone()));
+            let case =
+                Expr::complit(Type::raw(""), names.zip([Expr::str(name)].into_iter().chain(vals)));
+            Expr(format!("    {case:#},\n"), case.1)
+        });
+        let cases = Expr::join(cases, "");
+        let subtest = Expr::closure(Signature::new().param("t", Type::raw("*testing.T")), tt.body);
+        let run = Expr::raw("t.Run").call([Expr::raw("tt.name"), subtest]);
+        let body = Block::new()
+            .push(Stmt(format!("tests := {ty}{{\n{cases:#}}}"), cases.1))
+            .push(Range::over(Expr::raw("tests")).value("tt").body(Block::new().push(run.into())));
+        Ok(Decl::test(tt.name, body))
+    }
+}
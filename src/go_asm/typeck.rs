@@ -0,0 +1,1454 @@
+use super::lexer::{Kind, Token, tokenize};
+use super::walker::{Stop, Walker, starts_type};
+use super::*;
+use std::collections::HashMap;
+
+/// Types of symbols from other packages, for [`Package::check_types`]. Symbols missing here are
+/// treated as unknown and never reported.
+#[derive(Debug, Clone, Default)]
+pub struct Externs {
+    values: HashMap<String, Type>,
+    types: HashMap<String, Type>,
+}
+
+impl Externs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A function, variable or constant. e.g. `("strconv.Itoa", Type::fn_ptr(sig))`
+    pub fn value(mut self, name: impl Display, ty: Type) -> Self {
+        self.values.insert(name.to_string(), ty);
+        self
+    }
+
+    /// A named type and its underlying type. e.g. `("time.Duration", Type::int(64))`
+    pub fn named(mut self, name: impl Display, underlying: Type) -> Self {
+        self.types.insert(name.to_string(), underlying);
+        self
+    }
+}
+
+impl Package {
+    /// Infer the types of expressions and report mismatched operands, constants that overflow
+    /// their type and assignments that need a conversion. Expressions whose type can't be
+    /// inferred, e.g. calls into packages missing from `externs`, are skipped.
+    pub fn check_types(&self, externs: &Externs) -> Result<(), Vec<Diagnostic>> {
+        let src = self.to_string();
+        let mut c = Checker::new(&src, externs);
+        c.run();
+        if c.diags.is_empty() { Ok(()) } else { Err(c.diags) }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Untyped {
+    Bool,
+    Int,
+    Rune,
+    Float,
+    Str,
+    Nil,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Ty {
+    Unknown,
+    /// Untyped constant, with its value if it is a known integer.
+    Untyped(Untyped, Option<i128>),
+    Basic(String),
+    Named(String),
+    Pointer(Box<Ty>),
+    Slice(Box<Ty>),
+    Array(Box<Ty>),
+    Map(Box<Ty>, Box<Ty>),
+    Chan(Box<Ty>),
+    Func(Vec<Ty>, bool, Vec<Ty>),
+    Struct(Vec<(String, Ty)>),
+    Interface,
+    /// Results of a call returning several values.
+    Tuple(Vec<Ty>),
+}
+
+const BASIC: [&str; 19] = [
+    "bool",
+    "string",
+    "int",
+    "int8",
+    "int16",
+    "int32",
+    "int64",
+    "uint",
+    "uint8",
+    "uint16",
+    "uint32",
+    "uint64",
+    "uintptr",
+    "float32",
+    "float64",
+    "complex64",
+    "complex128",
+    "byte",
+    "rune",
+];
+
+const BUILTINS: [&str; 18] = [
+    "append", "cap", "clear", "close", "complex", "copy", "delete", "imag", "len", "make", "max",
+    "min", "new", "panic", "print", "println", "real", "recover",
+];
+
+impl Ty {
+    fn boxed(self) -> Box<Ty> {
+        Box::new(self)
+    }
+
+    /// Range of an integer type.
+    fn int_range(name: &str) -> Option<(i128, i128)> {
+        let bits = |s: &str| s.parse::<u32>().unwrap_or(64);
+        if let Some(b) = name.strip_prefix("uint") {
+            let b = if b == "ptr" { 64 } else { bits(b) };
+            Some((0, (1i128 << b) - 1))
+        } else if let Some(b) = name.strip_prefix("int") {
+            let b = bits(b);
+            Some((-(1i128 << (b - 1)), (1i128 << (b - 1)) - 1))
+        } else {
+            None
+        }
+    }
+
+    fn is_numeric(name: &str) -> bool {
+        Self::int_range(name).is_some() || name.starts_with("float") || name.starts_with("complex")
+    }
+}
+
+impl Display for Ty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |tys: &[Ty]| tys.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(", ");
+        match self {
+            Ty::Unknown => write!(f, "invalid type"),
+            Ty::Untyped(kind, _) => {
+                let kind = match kind {
+                    Untyped::Bool => "bool",
+                    Untyped::Int => "int",
+                    Untyped::Rune => "rune",
+                    Untyped::Float => "float",
+                    Untyped::Str => "string",
+                    Untyped::Nil => return write!(f, "untyped nil"),
+                };
+                write!(f, "untyped {kind}")
+            }
+            Ty::Basic(name) | Ty::Named(name) => write!(f, "{name}"),
+            Ty::Pointer(t) => write!(f, "*{t}"),
+            Ty::Slice(t) => write!(f, "[]{t}"),
+            Ty::Array(t) => write!(f, "[...]{t}"),
+            Ty::Map(k, v) => write!(f, "map[{k}]{v}"),
+            Ty::Chan(t) => write!(f, "chan {t}"),
+            Ty::Func(params, _, results) => match results.as_slice() {
+                [] => write!(f, "func({})", list(params)),
+                [ret] => write!(f, "func({}) {ret}", list(params)),
+                rets => write!(f, "func({}) ({})", list(params), list(rets)),
+            },
+            Ty::Struct(_) => write!(f, "struct{{...}}"),
+            Ty::Interface => write!(f, "interface{{...}}"),
+            Ty::Tuple(tys) => write!(f, "({})", list(tys)),
+        }
+    }
+}
+
+/// Walks over the package: types and signatures first, then package-level values so that
+/// bodies see them wherever they are declared, then function bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pass {
+    Collect,
+    Values,
+    Bodies,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ValKind {
+    Value,
+    Type,
+    Builtin(String),
+    /// An unresolved name followed by `.`, taken for an imported package.
+    Package(String),
+}
+
+/// An inferred expression, with its byte range in the source for messages.
+#[derive(Debug, Clone)]
+struct Val {
+    ty: Ty,
+    kind: ValKind,
+    start: usize,
+    end: usize,
+}
+
+struct Checker<'a> {
+    src: &'a str,
+    toks: Vec<Token>,
+    pos: usize,
+    diags: Vec<Diagnostic>,
+    path: Vec<String>,
+    externs: &'a Externs,
+    /// Underlying types of the package's named types.
+    types: HashMap<String, Ty>,
+    aliases: HashMap<String, Ty>,
+    methods: HashMap<(String, String), Ty>,
+    globals: HashMap<String, Ty>,
+    scopes: Vec<HashMap<String, Ty>>,
+    /// Result types of the enclosing functions, innermost last.
+    results: Vec<Vec<Ty>>,
+}
+
+impl Walker for Checker<'_> {
+    fn toks(&self) -> &[Token] {
+        &self.toks
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn pos_mut(&mut self) -> &mut usize {
+        &mut self.pos
+    }
+}
+
+impl<'a> Checker<'a> {
+    /// Skip to the end of the statement, leaving a closing bracket in place.
+    fn skip_stmt(&mut self) {
+        self.pos = self.stmt_end(self.pos, Stop::Stmt);
+    }
+
+    fn new(src: &'a str, externs: &'a Externs) -> Self {
+        Self {
+            src,
+            toks: tokenize(src),
+            pos: 0,
+            diags: Vec::new(),
+            path: Vec::new(),
+            externs,
+            types: HashMap::new(),
+            aliases: HashMap::new(),
+            methods: HashMap::new(),
+            globals: HashMap::new(),
+            scopes: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    fn diag(&mut self, kind: DiagnosticKind, start: usize, message: String) {
+        let line = self.src[..start.min(self.src.len())].matches('\n').count() + 1;
+        self.diags.push(Diagnostic { kind, path: self.path.clone(), line, message });
+    }
+
+    fn text(&self, val: &Val) -> &str {
+        &self.src[val.start..val.end.max(val.start)]
+    }
+
+    /// Byte offset after the last consumed token.
+    fn prev_end(&self) -> usize {
+        self.pos.checked_sub(1).map_or(0, |i| self.tok(i).end)
+    }
+
+    fn at_end(&self) -> bool {
+        let tok = self.peek();
+        tok.kind == Kind::Semi || tok.kind == Kind::Eof || tok.is("}") || tok.is(")")
+    }
+
+    fn run(&mut self) {
+        for (name, ty) in &self.externs.types {
+            let ty = self.parse_type_str(&ty.0);
+            self.types.insert(name.clone(), ty);
+        }
+        for pass in [Pass::Collect, Pass::Values, Pass::Bodies] {
+            self.pos = 0;
+            self.top_level(pass);
+        }
+    }
+
+    fn parse_type_str(&mut self, src: &str) -> Ty {
+        let toks = std::mem::replace(&mut self.toks, tokenize(src));
+        let pos = std::mem::replace(&mut self.pos, 0);
+        let ty = self.parse_type();
+        (self.toks, self.pos) = (toks, pos);
+        ty
+    }
+
+    fn top_level(&mut self, pass: Pass) {
+        loop {
+            let tok = self.peek().clone();
+            match tok.text.as_str() {
+                _ if tok.kind == Kind::Eof => break,
+                "type" if tok.kind == Kind::Keyword => {
+                    self.bump();
+                    self.group(|c| c.type_spec(pass == Pass::Collect));
+                }
+                "var" | "const" if tok.kind == Kind::Keyword => {
+                    self.bump();
+                    let is_const = tok.text == "const";
+                    let mut last = None;
+                    self.group(|c| c.value_spec(is_const, pass, &mut last));
+                }
+                "func" if tok.kind == Kind::Keyword => {
+                    self.bump();
+                    self.func_decl(pass);
+                }
+                _ => {
+                    self.bump();
+                    self.skip_stmt();
+                }
+            }
+            self.skip_semis();
+        }
+    }
+
+    fn group(&mut self, mut spec: impl FnMut(&mut Self)) {
+        self.specs(|c| {
+            spec(c);
+            c.skip_stmt();
+        });
+    }
+
+    fn type_spec(&mut self, collect: bool) {
+        let name = self.bump().text;
+        if self.peek().is("[")
+            && self.tok(self.pos + 1).kind == Kind::Ident
+            && !self.tok(self.pos + 2).is("]")
+        {
+            // generic types are left unknown.
+            self.pos = self.balanced_end(self.pos);
+            self.skip_stmt();
+            self.types.insert(name, Ty::Unknown);
+            return;
+        }
+        let alias = self.eat("=");
+        let ty = self.parse_type();
+        if collect || !self.scopes.is_empty() {
+            if alias {
+                self.aliases.insert(name, ty);
+            } else {
+                self.types.insert(name, ty);
+            }
+        }
+    }
+
+    /// A `var` or `const` spec. `last` is the type and values of the previous const spec, for
+    /// implicit repetition in groups.
+    fn value_spec(&mut self, is_const: bool, pass: Pass, last: &mut Option<(Ty, Vec<Val>)>) {
+        if pass == Pass::Bodies {
+            return;
+        }
+        let mut names = vec![self.bump().text];
+        while self.eat(",") {
+            names.push(self.bump().text);
+        }
+        let declared = (!self.peek().is("=") && !self.at_end()).then(|| self.parse_type());
+        if pass == Pass::Collect {
+            if let Some(ty) = &declared {
+                for name in &names {
+                    self.globals.insert(name.clone(), ty.clone());
+                }
+            }
+            return;
+        }
+        let vals = if self.eat("=") {
+            self.expr_list(false)
+        } else if let (true, Some((ty, vals))) = (is_const && declared.is_none(), last.as_ref()) {
+            // implicit repetition, the values are usually iota based.
+            let ty = if *ty == Ty::Unknown { Ty::Untyped(Untyped::Int, None) } else { ty.clone() };
+            let vals = vals.iter().map(|v| Val { ty: ty.clone(), ..v.clone() }).collect();
+            self.bind_values(&names, Some(&ty), vals, "");
+            return;
+        } else {
+            Vec::new()
+        };
+        if is_const {
+            *last = Some((declared.clone().unwrap_or(Ty::Unknown), vals.clone()));
+        }
+        let ctx = if is_const { "constant declaration" } else { "variable declaration" };
+        self.bind_values(&names, declared.as_ref(), vals, ctx);
+    }
+
+    /// Bind `names` to `vals`, checked against the declared type if any.
+    fn bind_values(&mut self, names: &[String], declared: Option<&Ty>, vals: Vec<Val>, ctx: &str) {
+        let vals = self.spread(vals, names.len());
+        for (i, name) in names.iter().enumerate() {
+            let ty = match (declared, vals.get(i)) {
+                (Some(ty), Some(val)) => {
+                    if !ctx.is_empty() {
+                        self.assign_check(val, ty, ctx);
+                    }
+                    ty.clone()
+                }
+                (Some(ty), None) => ty.clone(),
+                (None, Some(val)) if ctx == "constant declaration" => val.ty.clone(),
+                (None, Some(val)) => Self::default_type(&val.ty),
+                (None, None) => Ty::Unknown,
+            };
+            self.bind(name, ty);
+        }
+    }
+
+    /// Expand a single call returning several values.
+    fn spread(&self, vals: Vec<Val>, want: usize) -> Vec<Val> {
+        match vals.as_slice() {
+            [Val { ty: Ty::Tuple(tys), .. }] if want > 1 => {
+                tys.iter().map(|ty| Val { ty: ty.clone(), ..vals[0].clone() }).collect()
+            }
+            _ => vals,
+        }
+    }
+
+    fn bind(&mut self, name: &str, ty: Ty) {
+        if name == "_" {
+            return;
+        }
+        match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.to_string(), ty),
+            None => self.globals.insert(name.to_string(), ty),
+        };
+    }
+
+    fn func_decl(&mut self, pass: Pass) {
+        let mut recv = None;
+        if self.peek().is("(") {
+            let end = self.balanced_end(self.pos);
+            let params = self.params();
+            recv = params.into_iter().next();
+            self.pos = end;
+        }
+        let name = self.bump().text;
+        let generic = self.peek().is("[");
+        if generic {
+            self.pos = self.balanced_end(self.pos);
+        }
+        let recv_ty = recv.as_ref().map(|(_, ty)| match ty {
+            Ty::Pointer(t) => t.to_string(),
+            t => t.to_string(),
+        });
+        let (params, variadic, results) = self.signature();
+        if pass == Pass::Collect {
+            let ty = if generic {
+                Ty::Unknown
+            } else {
+                let params = params.iter().map(|(_, t)| t.clone()).collect();
+                Ty::Func(params, variadic, results.iter().map(|(_, t)| t.clone()).collect())
+            };
+            match &recv_ty {
+                Some(recv) => self.methods.insert((recv.clone(), name.clone()), ty),
+                None => self.globals.insert(name.clone(), ty),
+            };
+        }
+        if pass != Pass::Bodies {
+            if self.peek().is("{") {
+                self.pos = self.balanced_end(self.pos);
+            }
+            return;
+        }
+        self.path.push(match &recv_ty {
+            Some(recv) => format!("method {recv}.{name}"),
+            None => format!("func {name}"),
+        });
+        if self.peek().is("{") {
+            if generic {
+                self.pos = self.balanced_end(self.pos);
+            } else {
+                self.scopes.push(HashMap::new());
+                for (name, ty) in recv.into_iter().chain(params).chain(results.clone()) {
+                    self.bind(&name, ty);
+                }
+                self.results.push(results.into_iter().map(|(_, t)| t).collect());
+                self.body();
+                self.results.pop();
+                self.scopes.pop();
+            }
+        }
+        self.path.pop();
+    }
+
+    /// Parameters and results, the last parameter is a slice if variadic.
+    #[allow(clippy::type_complexity)]
+    fn signature(&mut self) -> (Vec<(String, Ty)>, bool, Vec<(String, Ty)>) {
+        let variadic = self.peek().is("(") && {
+            let end = self.balanced_end(self.pos);
+            (self.pos..end).any(|i| self.tok(i).is("..."))
+        };
+        let params = self.params();
+        let results = if self.peek().is("(") {
+            self.params()
+        } else if starts_type(self.peek()) {
+            vec![(String::new(), self.parse_type())]
+        } else {
+            Vec::new()
+        };
+        (params, variadic, results)
+    }
+
+    /// A parenthesized parameter list, names are empty when unnamed.
+    fn params(&mut self) -> Vec<(String, Ty)> {
+        if !self.eat("(") {
+            return Vec::new();
+        }
+        let mut elems: Vec<(Option<String>, Option<Ty>)> = Vec::new();
+        while !self.eat(")") && self.peek().kind != Kind::Eof {
+            let named = self.peek().kind == Kind::Ident && {
+                let next = self.tok(self.pos + 1);
+                !next.is(".") && !next.is(",") && !next.is(")")
+            };
+            let lone = self.peek().kind == Kind::Ident
+                && [",", ")"].iter().any(|t| self.tok(self.pos + 1).is(t));
+            if named {
+                let name = self.bump().text;
+                let variadic = self.eat("...");
+                let ty = self.parse_type();
+                let ty = if variadic { Ty::Slice(ty.boxed()) } else { ty };
+                elems.push((Some(name), Some(ty)));
+            } else if lone {
+                // a name sharing the type of the next entry, or an unnamed type.
+                let name = self.bump().text;
+                elems.push((Some(name), None));
+            } else {
+                let variadic = self.eat("...");
+                let ty = self.parse_type();
+                elems.push((None, Some(if variadic { Ty::Slice(ty.boxed()) } else { ty })));
+            }
+            if !self.eat(",") && !self.peek().is(")") {
+                self.pos = self.balanced_end(self.pos.saturating_sub(1)).max(self.pos + 1);
+            }
+        }
+        let named = elems.iter().any(|(n, t)| n.is_some() && t.is_some());
+        let mut res = Vec::new();
+        let mut pending = Vec::new();
+        for (name, ty) in elems {
+            match (name, ty, named) {
+                (Some(name), None, true) => pending.push(name),
+                (Some(name), None, false) => {
+                    let ty = self.parse_type_str(&name);
+                    res.push((String::new(), ty));
+                }
+                (name, Some(ty), _) => {
+                    for name in pending.drain(..) {
+                        res.push((name, ty.clone()));
+                    }
+                    res.push((name.unwrap_or_default(), ty));
+                }
+                (None, None, _) => {}
+            }
+        }
+        res
+    }
+
+    fn parse_type(&mut self) -> Ty {
+        let tok = self.bump();
+        match tok.text.as_str() {
+            "*" => Ty::Pointer(self.parse_type().boxed()),
+            "[" => {
+                if self.eat("]") {
+                    return Ty::Slice(self.parse_type().boxed());
+                }
+                self.pos = self.balanced_end(self.pos - 1);
+                Ty::Array(self.parse_type().boxed())
+            }
+            "map" => {
+                self.eat("[");
+                let key = self.parse_type();
+                self.eat("]");
+                Ty::Map(key.boxed(), self.parse_type().boxed())
+            }
+            "chan" => {
+                self.eat("<-");
+                Ty::Chan(self.parse_type().boxed())
+            }
+            "<-" => {
+                self.eat("chan");
+                Ty::Chan(self.parse_type().boxed())
+            }
+            "func" => {
+                let (params, variadic, results) = self.signature();
+                let params = params.into_iter().map(|(_, t)| t).collect();
+                Ty::Func(params, variadic, results.into_iter().map(|(_, t)| t).collect())
+            }
+            "struct" => self.struct_fields(),
+            "interface" => {
+                if self.peek().is("{") {
+                    self.pos = self.balanced_end(self.pos);
+                }
+                Ty::Interface
+            }
+            "(" => {
+                let ty = self.parse_type();
+                self.eat(")");
+                ty
+            }
+            _ if tok.kind == Kind::Ident => {
+                let mut name = tok.text.clone();
+                if self.peek().is(".") && self.tok(self.pos + 1).kind == Kind::Ident {
+                    self.bump();
+                    name = format!("{name}.{}", self.bump().text);
+                }
+                if self.peek().is("[") && !self.tok(self.pos + 1).is("]") {
+                    // instantiated generic type.
+                    self.pos = self.balanced_end(self.pos);
+                    return Ty::Unknown;
+                }
+                self.named_type(&name).unwrap_or(Ty::Unknown)
+            }
+            _ => {
+                self.pos -= 1;
+                Ty::Unknown
+            }
+        }
+    }
+
+    /// The type a name refers to, if it is one.
+    fn named_type(&self, name: &str) -> Option<Ty> {
+        Some(match name {
+            "byte" => Ty::Basic("uint8".into()),
+            "rune" => Ty::Basic("int32".into()),
+            "any" | "comparable" => Ty::Interface,
+            "error" => Ty::Named("error".into()),
+            _ if BASIC.contains(&name) => Ty::Basic(name.into()),
+            _ if self.aliases.contains_key(name) => self.aliases[name].clone(),
+            _ if self.types.contains_key(name) => Ty::Named(name.into()),
+            _ => return None,
+        })
+    }
+
+    fn struct_fields(&mut self) -> Ty {
+        if !self.eat("{") {
+            return Ty::Unknown;
+        }
+        let mut fields = Vec::new();
+        loop {
+            while self.peek().kind == Kind::Semi || self.peek().is(",") {
+                self.bump();
+            }
+            if self.eat("}") || self.peek().kind == Kind::Eof {
+                break;
+            }
+            let next = self.tok(self.pos + 1);
+            let embedded = self.peek().is("*")
+                || next.kind == Kind::Semi
+                || next.is(".")
+                || next.is("}")
+                || next.kind == Kind::Literal;
+            if embedded {
+                let ty = self.parse_type();
+                let name =
+                    ty.to_string().trim_start_matches('*').rsplit('.').next().unwrap_or("").into();
+                fields.push((name, ty));
+            } else {
+                let mut names = vec![self.bump().text];
+                while self.eat(",") {
+                    names.push(self.bump().text);
+                }
+                let ty = self.parse_type();
+                fields.extend(names.into_iter().map(|n| (n, ty.clone())));
+            }
+            if self.peek().kind == Kind::Literal {
+                self.bump();
+            }
+            if !self.peek().is("}") && self.peek().kind != Kind::Semi {
+                self.bump();
+            }
+        }
+        Ty::Struct(fields)
+    }
+
+    fn underlying(&self, ty: &Ty) -> Ty {
+        let mut ty = ty.clone();
+        for _ in 0..16 {
+            match ty {
+                Ty::Named(name) if name == "error" => return Ty::Interface,
+                Ty::Named(name) => match self.types.get(&name) {
+                    Some(next) => ty = next.clone(),
+                    None => return Ty::Unknown,
+                },
+                ty => return ty,
+            }
+        }
+        Ty::Unknown
+    }
+
+    fn default_type(ty: &Ty) -> Ty {
+        match ty {
+            Ty::Untyped(kind, _) => match kind {
+                Untyped::Bool => Ty::Basic("bool".into()),
+                Untyped::Int => Ty::Basic("int".into()),
+                Untyped::Rune => Ty::Basic("int32".into()),
+                Untyped::Float => Ty::Basic("float64".into()),
+                Untyped::Str => Ty::Basic("string".into()),
+                Untyped::Nil => Ty::Unknown,
+            },
+            Ty::Tuple(_) => Ty::Unknown,
+            ty => ty.clone(),
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<Ty> {
+        self.scopes.iter().rev().find_map(|s| s.get(name)).or(self.globals.get(name)).cloned()
+    }
+
+    fn body(&mut self) {
+        if !self.eat("{") {
+            return;
+        }
+        self.stmt_list();
+        self.eat("}");
+    }
+
+    fn block(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.body();
+        self.scopes.pop();
+    }
+
+    fn stmt_list(&mut self) {
+        loop {
+            self.skip_semis();
+            let tok = self.peek();
+            if tok.kind == Kind::Eof || tok.is("}") || tok.is("case") || tok.is("default") {
+                return;
+            }
+            let pos = self.pos;
+            self.stmt();
+            self.skip_stmt();
+            if self.pos == pos {
+                self.bump();
+            }
+        }
+    }
+
+    fn stmt(&mut self) {
+        let tok = self.peek().clone();
+        if tok.kind == Kind::Ident && self.tok(self.pos + 1).is(":") {
+            self.pos += 2;
+            self.skip_semis();
+            return self.stmt();
+        }
+        match tok.text.as_str() {
+            "{" if tok.kind == Kind::Punct => self.block(),
+            "var" | "const" if tok.kind == Kind::Keyword => {
+                self.bump();
+                let is_const = tok.text == "const";
+                let mut last = None;
+                self.group(|c| c.value_spec(is_const, Pass::Values, &mut last));
+            }
+            "type" if tok.kind == Kind::Keyword => {
+                self.bump();
+                self.group(|c| c.type_spec(false));
+            }
+            "return" if tok.kind == Kind::Keyword => {
+                self.bump();
+                let vals = if self.at_end() { Vec::new() } else { self.expr_list(false) };
+                let results = self.results.last().cloned().unwrap_or_default();
+                let vals = self.spread(vals, results.len());
+                if vals.len() == results.len() {
+                    for (val, ty) in vals.iter().zip(&results) {
+                        self.assign_check(val, ty, "return statement");
+                    }
+                }
+            }
+            "go" | "defer" if tok.kind == Kind::Keyword => {
+                self.bump();
+                self.expr(false);
+            }
+            "if" if tok.kind == Kind::Keyword => self.if_stmt(),
+            "for" if tok.kind == Kind::Keyword => self.for_stmt(),
+            "switch" | "select" if tok.kind == Kind::Keyword => self.switch_stmt(),
+            _ if tok.kind == Kind::Keyword => self.skip_stmt(),
+            _ => self.simple_stmt(false),
+        }
+    }
+
+    fn if_stmt(&mut self) {
+        self.bump();
+        self.scopes.push(HashMap::new());
+        self.simple_stmt(true);
+        if self.eat(";") {
+            self.simple_stmt(true);
+        }
+        self.block();
+        if self.eat("else") {
+            if self.peek().is("if") { self.if_stmt() } else { self.block() }
+        }
+        self.scopes.pop();
+    }
+
+    fn for_stmt(&mut self) {
+        self.bump();
+        self.scopes.push(HashMap::new());
+        if !self.peek().is("{") {
+            if !self.peek().is(";") {
+                self.simple_stmt(true);
+            }
+            if self.eat(";") {
+                if !self.peek().is(";") {
+                    self.expr(true);
+                }
+                self.eat(";");
+                if !self.peek().is("{") {
+                    self.simple_stmt(true);
+                }
+            }
+        }
+        self.block();
+        self.scopes.pop();
+    }
+
+    fn switch_stmt(&mut self) {
+        let select = self.bump().text == "select";
+        self.scopes.push(HashMap::new());
+        let mut type_switch = false;
+        if !self.peek().is("{") {
+            let end = self.header_end(self.pos);
+            type_switch = (self.pos..end).any(|i| {
+                self.tok(i).is(".") && self.tok(i + 1).is("(") && self.tok(i + 2).is("type")
+            });
+            if type_switch {
+                if self.tok(self.pos + 1).is(":=") {
+                    let name = self.bump().text;
+                    self.bind(&name, Ty::Unknown);
+                }
+                self.pos = end;
+            } else {
+                self.simple_stmt(true);
+                if self.eat(";") && !self.peek().is("{") {
+                    self.simple_stmt(true);
+                }
+            }
+        }
+        self.eat("{");
+        loop {
+            self.skip_semis();
+            if self.peek().is("}") || self.peek().kind == Kind::Eof {
+                break;
+            }
+            self.scopes.push(HashMap::new());
+            if self.eat("case") {
+                if select {
+                    self.simple_stmt(false);
+                } else if type_switch {
+                    while !self.peek().is(":") && self.peek().kind != Kind::Eof {
+                        self.pos = self.balanced_end(self.pos);
+                    }
+                } else {
+                    self.expr_list(false);
+                }
+            } else {
+                self.bump();
+            }
+            self.eat(":");
+            self.stmt_list();
+            self.scopes.pop();
+        }
+        self.eat("}");
+        self.scopes.pop();
+    }
+
+    /// An expression, assignment, definition, send or inc/dec statement.
+    fn simple_stmt(&mut self, header: bool) {
+        if self.at_end() || self.peek().is("{") {
+            return;
+        }
+        let lhs = self.expr_list(header);
+        let op = self.peek().clone();
+        if op.kind != Kind::Punct {
+            return;
+        }
+        match op.text.as_str() {
+            ":=" | "=" => {
+                self.bump();
+                if self.eat("range") {
+                    let iter = self.expr(header);
+                    let (key, value) = self.range_types(&iter.ty);
+                    for (val, ty) in lhs.iter().zip([key, value]) {
+                        self.assign_or_define(&op.text, val, ty);
+                    }
+                    return;
+                }
+                let rhs = self.expr_list(header);
+                let rhs = self.spread(rhs, lhs.len());
+                if rhs.len() != lhs.len() {
+                    return;
+                }
+                for (l, r) in lhs.iter().zip(rhs) {
+                    if op.text == "=" {
+                        self.assign_check(&r, &l.ty, "assignment");
+                    } else {
+                        self.assign_or_define(":=", l, Self::default_type(&r.ty));
+                    }
+                }
+            }
+            "++" | "--" => {
+                self.bump();
+            }
+            "<-" => {
+                self.bump();
+                let val = self.expr(header);
+                if let [ch] = lhs.as_slice()
+                    && let Ty::Chan(elem) = self.underlying(&ch.ty)
+                {
+                    self.assign_check(&val, &elem, "send");
+                }
+            }
+            text if text.len() >= 2
+                && text.ends_with('=')
+                && !["==", "!=", "<=", ">="].contains(&text) =>
+            {
+                self.bump();
+                let rhs = self.expr(header);
+                if let [lhs] = lhs.as_slice() {
+                    self.binop(lhs.clone(), &text[..text.len() - 1], rhs);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn assign_or_define(&mut self, op: &str, lhs: &Val, ty: Ty) {
+        let name = self.text(lhs).to_string();
+        let defined = self.scopes.last().is_some_and(|s| s.contains_key(&name));
+        if op == ":=" && !defined {
+            self.bind(&name, ty);
+        } else {
+            let val = Val { ty, ..lhs.clone() };
+            self.assign_check(&val, &lhs.ty, "assignment");
+        }
+    }
+
+    /// Key and value types of a range clause.
+    fn range_types(&self, ty: &Ty) -> (Ty, Ty) {
+        let int = Ty::Basic("int".into());
+        match self.underlying(ty) {
+            Ty::Slice(elem) | Ty::Array(elem) => (int, *elem),
+            Ty::Pointer(t) => match self.underlying(&t) {
+                Ty::Array(elem) => (int, *elem),
+                _ => (Ty::Unknown, Ty::Unknown),
+            },
+            Ty::Basic(b) if b == "string" => (int, Ty::Basic("int32".into())),
+            Ty::Basic(b) if Ty::int_range(&b).is_some() => (ty.clone(), Ty::Unknown),
+            Ty::Untyped(Untyped::Int, _) => (int, Ty::Unknown),
+            Ty::Untyped(Untyped::Str, _) => (int, Ty::Basic("int32".into())),
+            Ty::Map(k, v) => (*k, *v),
+            Ty::Chan(elem) => (*elem, Ty::Unknown),
+            _ => (Ty::Unknown, Ty::Unknown),
+        }
+    }
+
+    fn expr_list(&mut self, header: bool) -> Vec<Val> {
+        let mut vals = vec![self.expr(header)];
+        while self.eat(",") {
+            vals.push(self.expr(header));
+        }
+        vals
+    }
+
+    fn expr(&mut self, header: bool) -> Val {
+        self.binary(1, header)
+    }
+
+    fn precedence(tok: &Token) -> usize {
+        if tok.kind != Kind::Punct {
+            return 0;
+        }
+        match tok.text.as_str() {
+            "||" => 1,
+            "&&" => 2,
+            "==" | "!=" | "<" | "<=" | ">" | ">=" => 3,
+            "+" | "-" | "|" | "^" => 4,
+            "*" | "/" | "%" | "<<" | ">>" | "&" | "&^" => 5,
+            _ => 0,
+        }
+    }
+
+    fn binary(&mut self, min: usize, header: bool) -> Val {
+        let mut lhs = self.unary(header);
+        loop {
+            let prec = Self::precedence(self.peek());
+            if prec < min || prec == 0 {
+                return lhs;
+            }
+            let op = self.bump().text;
+            let rhs = self.binary(prec + 1, header);
+            lhs = self.binop(lhs, &op, rhs);
+        }
+    }
+
+    fn binop(&mut self, lhs: Val, op: &str, rhs: Val) -> Val {
+        let (start, end) = (lhs.start, rhs.end.max(lhs.end));
+        let val = |ty| Val { ty, kind: ValKind::Value, start, end };
+        match op {
+            "&&" | "||" => return val(Ty::Untyped(Untyped::Bool, None)),
+            "<<" | ">>" => {
+                return match (&lhs.ty, &rhs.ty) {
+                    (Ty::Untyped(kind, Some(l)), Ty::Untyped(_, Some(r))) if *r < 128 => {
+                        // `checked_shl` only checks the shift count, not the bits shifted out.
+                        let v = match op {
+                            "<<" => l.checked_shl(*r as u32).filter(|v| v >> r == *l),
+                            _ => Some(l >> r),
+                        };
+                        val(Ty::Untyped(*kind, v))
+                    }
+                    _ => val(lhs.ty),
+                };
+            }
+            _ => {}
+        }
+        let cmp = ["==", "!=", "<", "<=", ">", ">="].contains(&op);
+        let ty = match (&lhs.ty, &rhs.ty) {
+            (Ty::Unknown, _) | (_, Ty::Unknown) | (Ty::Tuple(_), _) | (_, Ty::Tuple(_)) => {
+                Ty::Unknown
+            }
+            (Ty::Untyped(l, lv), Ty::Untyped(r, rv)) => {
+                let kind = if *l == Untyped::Float || *r == Untyped::Float {
+                    Untyped::Float
+                } else if *l == Untyped::Rune || *r == Untyped::Rune {
+                    Untyped::Rune
+                } else {
+                    *l
+                };
+                let v = match (lv, rv) {
+                    (Some(l), Some(r)) => fold(*l, op, *r),
+                    _ => None,
+                };
+                Ty::Untyped(kind, v)
+            }
+            (Ty::Untyped(..), typed) => {
+                self.assign_check(&lhs, typed, "");
+                typed.clone()
+            }
+            (typed, Ty::Untyped(..)) => {
+                self.assign_check(&rhs, typed, "");
+                typed.clone()
+            }
+            (l, r) => {
+                let ul = self.underlying(l);
+                let ur = self.underlying(r);
+                let skip = ul == Ty::Unknown || ur == Ty::Unknown;
+                let iface = cmp && (ul == Ty::Interface || ur == Ty::Interface);
+                if l != r && !skip && !iface {
+                    let msg = format!(
+                        "invalid operation: {} (mismatched types {l} and {r})",
+                        &self.src[start..end]
+                    );
+                    self.diag(DiagnosticKind::TypeMismatch, start, msg);
+                }
+                l.clone()
+            }
+        };
+        if cmp { val(Ty::Untyped(Untyped::Bool, None)) } else { val(ty) }
+    }
+
+    /// Report if `val` can't be assigned to `target`. An empty `ctx` is for operands of binary
+    /// operations, where untyped constants are converted.
+    fn assign_check(&mut self, val: &Val, target: &Ty, ctx: &str) {
+        let under = self.underlying(target);
+        if val.ty == Ty::Unknown || under == Ty::Unknown || matches!(val.ty, Ty::Tuple(_)) {
+            return;
+        }
+        let text = self.text(val).to_string();
+        let in_ctx = if ctx.is_empty() { String::new() } else { format!(" in {ctx}") };
+        match &val.ty {
+            Ty::Untyped(kind, value) => {
+                let ok = match (&under, kind) {
+                    (Ty::Interface, _) => true,
+                    (Ty::Basic(b), Untyped::Bool) => b == "bool",
+                    (Ty::Basic(b), Untyped::Str) => b == "string",
+                    (Ty::Basic(b), Untyped::Int | Untyped::Rune | Untyped::Float) => {
+                        if let (Some(v), Some((lo, hi))) = (value, Ty::int_range(b))
+                            && (*v < lo || *v > hi)
+                        {
+                            let kind = if *kind == Untyped::Rune { "rune" } else { "int" };
+                            let msg = format!(
+                                "cannot use {text} (untyped {kind} constant {v}) as {target} \
+                                 value{in_ctx} (overflows)"
+                            );
+                            self.diag(DiagnosticKind::Overflow, val.start, msg);
+                            return;
+                        }
+                        Ty::is_numeric(b)
+                    }
+                    (Ty::Basic(_), Untyped::Nil) => false,
+                    (Ty::Struct(_) | Ty::Array(_), _) => false,
+                    (_, Untyped::Nil) => true,
+                    _ => false,
+                };
+                if !ok {
+                    let msg = format!("cannot use {text} ({}) as {target} value{in_ctx}", val.ty);
+                    self.diag(DiagnosticKind::TypeMismatch, val.start, msg);
+                }
+            }
+            ty => {
+                if ctx.is_empty() || ty == target || under == Ty::Interface {
+                    return;
+                }
+                let from = self.underlying(ty);
+                let unnamed = |t: &Ty| !matches!(t, Ty::Named(_) | Ty::Basic(_));
+                if from == Ty::Unknown || ((unnamed(ty) || unnamed(target)) && from == under) {
+                    return;
+                }
+                let numeric = |t: &Ty| matches!(t, Ty::Basic(b) if Ty::is_numeric(b));
+                if numeric(&from) && numeric(&under) {
+                    let msg = format!(
+                        "cannot use {text} ({ty}) as {target} value{in_ctx}, \
+                         needs conversion {target}({text})"
+                    );
+                    self.diag(DiagnosticKind::NeedsConversion, val.start, msg);
+                } else {
+                    let msg = format!("cannot use {text} ({ty}) as {target} value{in_ctx}");
+                    self.diag(DiagnosticKind::TypeMismatch, val.start, msg);
+                }
+            }
+        }
+    }
+
+    fn unary(&mut self, header: bool) -> Val {
+        let tok = self.peek().clone();
+        if tok.kind == Kind::Punct
+            && ["-", "+", "!", "^", "&", "*", "<-"].contains(&tok.text.as_str())
+        {
+            self.bump();
+            let mut val = self.unary(header);
+            val.start = tok.start;
+            val.ty = match (tok.text.as_str(), val.ty) {
+                ("-", Ty::Untyped(kind, v)) => Ty::Untyped(kind, v.and_then(i128::checked_neg)),
+                ("^", Ty::Untyped(kind, v)) => Ty::Untyped(kind, v.map(|v| !v)),
+                ("!", _) => Ty::Untyped(Untyped::Bool, None),
+                ("&", ty) => Ty::Pointer(ty.boxed()),
+                ("*", ty) if val.kind == ValKind::Type => Ty::Pointer(ty.boxed()),
+                ("*", ty) => match self.underlying(&ty) {
+                    Ty::Pointer(t) => *t,
+                    _ => Ty::Unknown,
+                },
+                ("<-", ty) => match self.underlying(&ty) {
+                    Ty::Chan(t) => *t,
+                    _ => Ty::Unknown,
+                },
+                (_, ty) => ty,
+            };
+            return val;
+        }
+        let val = self.operand(header);
+        self.postfix(val, header)
+    }
+
+    fn operand(&mut self, header: bool) -> Val {
+        let tok = self.peek().clone();
+        let start = tok.start;
+        let mut kind = ValKind::Value;
+        let ty = match tok.text.as_str() {
+            _ if tok.kind == Kind::Literal => {
+                self.bump();
+                literal(&tok.text)
+            }
+            "(" if tok.kind == Kind::Punct => {
+                self.bump();
+                let val = self.expr(false);
+                self.eat(")");
+                kind = val.kind;
+                val.ty
+            }
+            "[" | "map" | "chan" | "struct" | "interface" if tok.kind != Kind::Ident => {
+                kind = ValKind::Type;
+                self.parse_type()
+            }
+            "func" if tok.kind == Kind::Keyword => {
+                self.bump();
+                let (params, variadic, results) = self.signature();
+                let ty = Ty::Func(
+                    params.iter().map(|(_, t)| t.clone()).collect(),
+                    variadic,
+                    results.iter().map(|(_, t)| t.clone()).collect(),
+                );
+                if self.peek().is("{") {
+                    self.path.push("func literal".into());
+                    self.scopes.push(HashMap::new());
+                    for (name, ty) in params.into_iter().chain(results.clone()) {
+                        self.bind(&name, ty);
+                    }
+                    self.results.push(results.into_iter().map(|(_, t)| t).collect());
+                    self.body();
+                    self.results.pop();
+                    self.scopes.pop();
+                    self.path.pop();
+                } else {
+                    kind = ValKind::Type;
+                }
+                ty
+            }
+            name if tok.kind == Kind::Ident => {
+                self.bump();
+                match name {
+                    "true" | "false" if self.lookup(name).is_none() => {
+                        Ty::Untyped(Untyped::Bool, None)
+                    }
+                    "nil" if self.lookup(name).is_none() => Ty::Untyped(Untyped::Nil, None),
+                    "iota" if self.lookup(name).is_none() => Ty::Untyped(Untyped::Int, None),
+                    _ => match self.lookup(name) {
+                        Some(ty) => ty,
+                        None => {
+                            if let Some(ty) = self.named_type(name) {
+                                kind = ValKind::Type;
+                                ty
+                            } else if BUILTINS.contains(&name) {
+                                kind = ValKind::Builtin(name.into());
+                                Ty::Unknown
+                            } else if self.peek().is(".") {
+                                kind = ValKind::Package(name.into());
+                                Ty::Unknown
+                            } else {
+                                Ty::Unknown
+                            }
+                        }
+                    },
+                }
+            }
+            _ => {
+                // not an expression, leave it to the statement.
+                return Val { ty: Ty::Unknown, kind, start, end: start };
+            }
+        };
+        let mut val = Val { ty, kind, start, end: self.prev_end() };
+        if !header && self.peek().is("{") && matches!(val.kind, ValKind::Type) {
+            // composite literal.
+            self.pos = self.balanced_end(self.pos);
+            val.kind = ValKind::Value;
+            val.end = self.prev_end();
+        }
+        val
+    }
+
+    fn postfix(&mut self, mut val: Val, header: bool) -> Val {
+        loop {
+            let tok = self.peek().clone();
+            match tok.text.as_str() {
+                "." if tok.kind == Kind::Punct => {
+                    self.bump();
+                    if self.eat("(") {
+                        val.ty = if self.eat("type") { Ty::Unknown } else { self.parse_type() };
+                        self.eat(")");
+                    } else {
+                        let name = self.bump().text;
+                        match &val.kind {
+                            ValKind::Package(pkg) => {
+                                let qualified = format!("{pkg}.{name}");
+                                if let Some(ty) = self.externs.values.get(&qualified) {
+                                    val.ty = self.parse_type_str(&ty.0.clone());
+                                    val.kind = ValKind::Value;
+                                } else if self.types.contains_key(&qualified) {
+                                    val.ty = Ty::Named(qualified);
+                                    val.kind = ValKind::Type;
+                                } else {
+                                    val.ty = Ty::Unknown;
+                                    val.kind = ValKind::Value;
+                                }
+                            }
+                            _ => {
+                                val.ty = self.select(&val.ty, &name);
+                                val.kind = ValKind::Value;
+                            }
+                        }
+                    }
+                    val.end = self.prev_end();
+                    if !header && val.kind == ValKind::Type && self.peek().is("{") {
+                        self.pos = self.balanced_end(self.pos);
+                        val.kind = ValKind::Value;
+                        val.end = self.prev_end();
+                    }
+                }
+                "(" if tok.kind == Kind::Punct => {
+                    self.bump();
+                    let mut args = Vec::new();
+                    while !self.peek().is(")") && self.peek().kind != Kind::Eof {
+                        let pos = self.pos;
+                        args.push(self.expr(false));
+                        self.eat("...");
+                        if !self.eat(",") && !self.peek().is(")") && self.pos == pos {
+                            self.bump();
+                        }
+                    }
+                    self.eat(")");
+                    val = self.call(val, args);
+                    val.end = self.prev_end();
+                }
+                "[" if tok.kind == Kind::Punct => {
+                    let open = self.pos;
+                    self.bump();
+                    let index = if self.peek().is(":") { None } else { Some(self.expr(false)) };
+                    let slice = self.peek().is(":");
+                    self.pos = self.balanced_end(open);
+                    let under = self.underlying(&val.ty);
+                    val.ty = match (slice, under) {
+                        (true, Ty::Basic(b)) if b == "string" => val.ty.clone(),
+                        (true, Ty::Untyped(Untyped::Str, _)) => Ty::Basic("string".into()),
+                        (true, Ty::Slice(_)) => val.ty.clone(),
+                        (true, Ty::Array(elem)) => Ty::Slice(elem),
+                        (false, Ty::Slice(elem) | Ty::Array(elem)) => *elem,
+                        (false, Ty::Basic(b)) if b == "string" => Ty::Basic("uint8".into()),
+                        (false, Ty::Map(key, elem)) => {
+                            if let Some(index) = &index {
+                                self.assign_check(index, &key, "map index");
+                            }
+                            *elem
+                        }
+                        _ => Ty::Unknown,
+                    };
+                    val.kind = ValKind::Value;
+                    val.end = self.prev_end();
+                }
+                _ => return val,
+            }
+        }
+    }
+
+    /// Field or method of a value.
+    fn select(&self, ty: &Ty, name: &str) -> Ty {
+        let base = match ty {
+            Ty::Pointer(t) => (**t).clone(),
+            t => t.clone(),
+        };
+        if let Ty::Named(type_name) = &base
+            && let Some(method) = self.methods.get(&(type_name.clone(), name.to_string()))
+        {
+            return method.clone();
+        }
+        match self.underlying(&base) {
+            Ty::Struct(fields) => {
+                if let Some((_, ty)) = fields.iter().find(|(n, _)| n == name) {
+                    return ty.clone();
+                }
+                // promoted through an embedded field.
+                for (field, ty) in &fields {
+                    if ty.to_string().trim_start_matches('*').ends_with(field.as_str()) {
+                        let found = self.select(ty, name);
+                        if found != Ty::Unknown {
+                            return found;
+                        }
+                    }
+                }
+                Ty::Unknown
+            }
+            _ => Ty::Unknown,
+        }
+    }
+
+    fn call(&mut self, callee: Val, args: Vec<Val>) -> Val {
+        let mut val = Val { ty: Ty::Unknown, kind: ValKind::Value, ..callee.clone() };
+        match &callee.kind {
+            ValKind::Type => {
+                if let [arg] = args.as_slice() {
+                    self.conversion(arg, &callee.ty);
+                }
+                val.ty = callee.ty;
+            }
+            ValKind::Builtin(name) => {
+                val.ty = match (name.as_str(), args.first()) {
+                    ("len" | "cap" | "copy", _) => Ty::Basic("int".into()),
+                    ("make", Some(arg)) => arg.ty.clone(),
+                    ("new", Some(arg)) => Ty::Pointer(arg.ty.clone().boxed()),
+                    ("append", Some(arg)) => arg.ty.clone(),
+                    ("min" | "max", Some(arg)) if args.iter().all(|a| a.ty == arg.ty) => {
+                        arg.ty.clone()
+                    }
+                    _ => Ty::Unknown,
+                };
+            }
+            _ => {
+                if let Ty::Func(params, variadic, results) = self.underlying(&callee.ty) {
+                    let args = self.spread(args, params.len());
+                    let fixed =
+                        if variadic { params.len().saturating_sub(1) } else { params.len() };
+                    let count_ok = if variadic { args.len() >= fixed } else { args.len() == fixed };
+                    if count_ok {
+                        for (i, arg) in args.iter().enumerate() {
+                            let param = match params.get(i) {
+                                Some(Ty::Slice(elem)) if variadic && i >= fixed => (**elem).clone(),
+                                Some(param) => param.clone(),
+                                None => match params.last() {
+                                    Some(Ty::Slice(elem)) => (**elem).clone(),
+                                    _ => Ty::Unknown,
+                                },
+                            };
+                            self.assign_check(arg, &param, "argument");
+                        }
+                    }
+                    val.ty = match results.len() {
+                        1 => results[0].clone(),
+                        _ => Ty::Tuple(results),
+                    };
+                }
+            }
+        }
+        val
+    }
+
+    /// Check an explicit conversion `T(x)` of a constant.
+    fn conversion(&mut self, arg: &Val, target: &Ty) {
+        if let (Ty::Untyped(Untyped::Int | Untyped::Rune, Some(v)), Ty::Basic(b)) =
+            (&arg.ty, self.underlying(target))
+            && let Some((lo, hi)) = Ty::int_range(&b)
+            && (*v < lo || *v > hi)
+        {
+            let msg = format!(
+                "cannot convert {} (untyped int constant {v}) to type {target} (overflows)",
+                self.text(arg)
+            );
+            self.diag(DiagnosticKind::Overflow, arg.start, msg);
+        }
+    }
+}
+
+/// Type and value of a literal.
+fn literal(text: &str) -> Ty {
+    if text.starts_with('"') || text.starts_with('`') {
+        return Ty::Untyped(Untyped::Str, None);
+    }
+    if let Some(rune) = text.strip_prefix('\'') {
+        let mut chars = rune.chars();
+        let value = match (chars.next(), chars.next()) {
+            (Some(c), Some('\'')) if c != '\\' => Some(c as i128),
+            _ => None,
+        };
+        return Ty::Untyped(Untyped::Rune, value);
+    }
+    let digits = text.replace('_', "");
+    let lower = digits.to_lowercase();
+    let (radix, body) = match lower.get(..2) {
+        Some("0x") => (16, &lower[2..]),
+        Some("0b") => (2, &lower[2..]),
+        Some("0o") => (8, &lower[2..]),
+        _ if lower.len() > 1
+            && lower.starts_with('0')
+            && lower.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            (8, &lower[1..])
+        }
+        _ => (10, lower.as_str()),
+    };
+    if lower.ends_with('i') {
+        return Ty::Unknown;
+    }
+    if radix == 10 && (body.contains('.') || body.contains('e'))
+        || radix == 16 && body.contains('p')
+    {
+        return Ty::Untyped(Untyped::Float, None);
+    }
+    Ty::Untyped(Untyped::Int, i128::from_str_radix(body, radix).ok())
+}
+
+/// Fold a binary operation on integer constants.
+fn fold(l: i128, op: &str, r: i128) -> Option<i128> {
+    match op {
+        "+" => l.checked_add(r),
+        "-" => l.checked_sub(r),
+        "*" => l.checked_mul(r),
+        "/" => l.checked_div(r),
+        "%" => l.checked_rem(r),
+        "&" => Some(l & r),
+        "|" => Some(l | r),
+        "^" => Some(l ^ r),
+        "&^" => Some(l & !r),
+        _ => None,
+    }
+}
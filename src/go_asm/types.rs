@@ -1,20 +1,249 @@
 use super::*;
 
-pub struct IfaceFn(pub String);
+/// A parameter or result of a [`Signature`]. An empty name means unnamed.
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+}
+
+impl Param {
+    pub fn new(name: impl Display, ty: Type) -> Self {
+        Self { name: name.to_string(), ty }
+    }
+}
+
+/// Parameters and results of a function. e.g. `(a int, b ...string) (n int, err error)`
+///
+/// Go requires a list to be either fully named or fully unnamed, so unnamed entries of a list
+/// that has any named entry are rendered as `_`.
+#[derive(Debug, Clone, Default)]
+pub struct Signature {
+    pub params: Vec<Param>,
+    /// The trailing `...T` parameter.
+    pub variadic: Option<Param>,
+    pub results: Vec<Param>,
+}
+
+impl Signature {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a parameter, `name` may be empty.
+    pub fn param(mut self, name: impl Display, ty: Type) -> Self {
+        self.params.push(Param::new(name, ty));
+        self
+    }
+
+    pub fn params(mut self, params: impl IntoIterator<Item = (impl Display, Type)>) -> Self {
+        self.params.extend(params.into_iter().map(|(name, ty)| Param::new(name, ty)));
+        self
+    }
+
+    /// Set the variadic parameter. e.g. `args ...any`
+    pub fn variadic(mut self, name: impl Display, ty: Type) -> Self {
+        self.variadic = Some(Param::new(name, ty));
+        self
+    }
+
+    /// Add a result, `name` may be empty.
+    pub fn result(mut self, name: impl Display, ty: Type) -> Self {
+        self.results.push(Param::new(name, ty));
+        self
+    }
+
+    pub fn results(mut self, results: impl IntoIterator<Item = (impl Display, Type)>) -> Self {
+        self.results.extend(results.into_iter().map(|(name, ty)| Param::new(name, ty)));
+        self
+    }
+
+    /// Whether both signatures have the same parameter and result types, ignoring names.
+    pub fn same_types(&self, other: &Self) -> bool {
+        fn types(list: &[Param]) -> Vec<String> {
+            list.iter().map(|p| p.ty.to_string()).collect()
+        }
+        types(&self.params) == types(&other.params)
+            && self.variadic.as_ref().map(|p| p.ty.to_string())
+                == other.variadic.as_ref().map(|p| p.ty.to_string())
+            && types(&self.results) == types(&other.results)
+    }
+
+    fn fmt_list<'a>(list: impl IntoIterator<Item = (&'a Param, bool)>) -> String {
+        let list: Vec<_> = list.into_iter().collect();
+        let named = list.iter().any(|(p, _)| !p.name.is_empty());
+        let list: Vec<_> = list
+            .into_iter()
+            .map(|(p, variadic)| {
+                let dots = if variadic { "..." } else { "" };
+                match (named, p.name.is_empty()) {
+                    (false, _) => format!("{dots}{}", p.ty),
+                    (true, true) => format!("_ {dots}{}", p.ty),
+                    (true, false) => format!("{} {dots}{}", p.name, p.ty),
+                }
+            })
+            .collect();
+        list.join(", ")
+    }
+}
+
+/// Named parameters and results, bound in the scope of the function body.
+impl Bindings for Signature {
+    fn bindings(&self) -> Vec<String> {
+        let params = self.params.iter().chain(&self.variadic).chain(&self.results);
+        params.map(|p| p.name.clone()).filter(|n| !n.is_empty() && n != "_").collect()
+    }
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params =
+            self.params.iter().map(|p| (p, false)).chain(self.variadic.iter().map(|p| (p, true)));
+        write!(f, "({})", Self::fmt_list(params))?;
+        match self.results.as_slice() {
+            [] => Ok(()),
+            [ret] if ret.name.is_empty() => write!(f, " {}", ret.ty),
+            rets => write!(f, " ({})", Self::fmt_list(rets.iter().map(|p| (p, false)))),
+        }
+    }
+}
+
+/// A method of an interface. e.g. `Read(p []byte) (n int, err error)`
+#[derive(Debug, Clone)]
+pub struct IfaceFn {
+    pub name: String,
+    pub sig: Signature,
+}
 
 impl IfaceFn {
-    pub fn new(
-        name: impl Display,
-        args: impl IntoIterator<Item = (impl Display, Type)>,
-        rets: impl IntoIterator<Item = Type>,
-    ) -> Self {
-        let args =
-            args.into_iter().map(|(arg, ty)| format!("{arg} {ty}")).collect::<Vec<_>>().join(", ");
-        let mut rets = rets.into_iter().map(|r| r.to_string()).collect::<Vec<_>>().join(", ");
-        if !rets.is_empty() {
-            rets = format!(" {rets}")
+    pub fn new(name: impl Display, sig: Signature) -> Self {
+        Self { name: name.to_string(), sig }
+    }
+}
+
+impl Display for IfaceFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.name, self.sig)
+    }
+}
+
+/// One `key:"value,option,..."` entry of a struct tag.
+#[derive(Debug, Clone)]
+pub struct Tag {
+    pub key: String,
+    pub value: String,
+    pub options: Vec<String>,
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut value = self.value.clone();
+        for opt in &self.options {
+            write!(value, ",{opt}")?;
+        }
+        write!(f, "{}:\"{}\"", self.key, value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// Struct field tags, rendered as `` `json:"id,omitempty" db:"id"` ``.
+#[derive(Debug, Clone, Default)]
+pub struct Tags(Vec<Tag>);
+
+impl Tags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.0
+    }
+
+    /// Add a tag. e.g. `tag("json", "id", ["omitempty"])`, quotes and backslashes of the value
+    /// and options are escaped.
+    pub fn tag(
+        mut self,
+        key: impl Display,
+        value: impl Display,
+        options: impl IntoIterator<Item = impl Display>,
+    ) -> Result<Self, Error> {
+        let key = key.to_string();
+        // the whole tag is a raw string literal, a key can't close it.
+        let invalid = |c: char| matches!(c, ' ' | '"' | '`' | ':') || c.is_control();
+        if key.is_empty() || key.chars().any(invalid) {
+            return Err(Error::InvalidTagKey(key));
+        }
+        if self.0.iter().any(|t| t.key == key) {
+            return Err(Error::DuplicateTagKey(key));
         }
-        Self(format!("{name}({args}) {rets}"))
+        let value = value.to_string();
+        let options: Vec<_> = options.into_iter().map(|o| o.to_string()).collect();
+        for v in std::iter::once(&value).chain(&options) {
+            if v.contains('`') || v.chars().any(|c| c.is_control()) {
+                return Err(Error::InvalidTagValue(v.clone()));
+            }
+        }
+        self.0.push(Tag { key, value, options });
+        Ok(self)
+    }
+}
+
+impl Display for Tags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let tags = self.0.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" ");
+        write!(f, "`{tags}`")
+    }
+}
+
+/// A struct field. e.g. `UserID string `json:"user_id"``
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    pub tags: Tags,
+}
+
+impl Field {
+    pub fn new(name: impl Display, ty: Type) -> Self {
+        Self { name: name.to_string(), ty, tags: Tags::new() }
+    }
+
+    pub fn tags(self, tags: Tags) -> Self {
+        Self { tags, ..self }
+    }
+
+    pub fn tag(
+        self,
+        key: impl Display,
+        value: impl Display,
+        options: impl IntoIterator<Item = impl Display>,
+    ) -> Result<Self, Error> {
+        Ok(Self { tags: self.tags.tag(key, value, options)?, ..self })
+    }
+
+    /// Add a tag whose value is the snake_case field name. e.g. `UserID` -> `json:"user_id"`
+    pub fn snake_tag(
+        self,
+        key: impl Display,
+        options: impl IntoIterator<Item = impl Display>,
+    ) -> Result<Self, Error> {
+        let value = crate::naming::snake_case(&self.name);
+        self.tag(key, value, options)
+    }
+}
+
+impl<N: Display> From<(N, Type)> for Field {
+    fn from((name, ty): (N, Type)) -> Self {
+        Self::new(name, ty)
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name, self.ty)?;
+        if !self.tags.0.is_empty() {
+            write!(f, " {}", self.tags)?;
+        }
+        Ok(())
     }
 }
 
@@ -58,9 +287,9 @@ impl Type {
         Self(format!("*{self}"))
     }
 
-    pub fn fn_ptr(self, args: impl IntoIterator<Item = Type>) -> Self {
-        let args = args.into_iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
-        Self(format!("func({args}) {self}"))
+    /// Create a function type. e.g. `func(int) (string, error)`
+    pub fn fn_ptr(sig: Signature) -> Self {
+        Self(format!("func{sig}"))
     }
 
     pub fn array(self, size: u32) -> Self {
@@ -76,7 +305,7 @@ impl Type {
         methods: impl IntoIterator<Item = IfaceFn>,
     ) -> Self {
         let embeds: String = embeds.into_iter().map(|e| format!("{e}\n")).collect();
-        let members: String = methods.into_iter().map(|m| format!("{}\n", m.0)).collect();
+        let members: String = methods.into_iter().map(|m| format!("{m}\n")).collect();
         if members.is_empty() && embeds.is_empty() {
             Self("interface{}".into())
         } else {
@@ -86,10 +315,10 @@ impl Type {
 
     pub fn struct_(
         embeds: impl IntoIterator<Item = impl Display>,
-        fields: impl IntoIterator<Item = (impl Display, Type)>,
+        fields: impl IntoIterator<Item = impl Into<Field>>,
     ) -> Self {
         let embeds: String = embeds.into_iter().map(|e| format!("{e}\n")).collect();
-        let members: String = fields.into_iter().map(|f| format!("{} {}\n", f.0, f.1)).collect();
+        let members: String = fields.into_iter().map(|f| format!("{}\n", f.into())).collect();
         if members.is_empty() && embeds.is_empty() {
             Self("struct{}".into())
         } else {
@@ -97,6 +326,27 @@ impl Type {
         }
     }
 
+    /// The zero value. e.g. `0`, `""`, `nil`, `struct{}{}` or `*new(T)` for named types.
+    pub fn zero(&self) -> Expr {
+        const NUMBERS: &str = "int int8 int16 int32 int64 uint uint8 uint16 uint32 uint64 uintptr \
+            byte rune float32 float64 complex64 complex128";
+        const NILABLE: [&str; 7] = ["*", "[]", "map[", "chan ", "<-chan ", "func(", "interface"];
+        let ty = self.0.as_str();
+        if NUMBERS.split_whitespace().any(|n| n == ty) {
+            Expr::raw("0")
+        } else if ty == "bool" {
+            Expr::raw("false")
+        } else if ty == "string" {
+            Expr::str("")
+        } else if ty == "error" || ty == "any" || NILABLE.iter().any(|p| ty.starts_with(p)) {
+            Expr::raw("nil")
+        } else if ty.starts_with("struct") || ty.starts_with('[') {
+            Expr::raw(format!("{ty}{{}}"))
+        } else {
+            Expr::raw(format!("*new({ty})"))
+        }
+    }
+
     /// Add a name.
     pub fn bind(self, name: impl Display) -> Self {
         Self(format!("{name} {self}"))
@@ -0,0 +1,876 @@
+use super::lexer::{Kind, Token, is_ident, tokenize};
+use super::walker::{Stop, Walker, starts_type};
+use super::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Code that doesn't parse, usually from `raw` nodes.
+    Syntax,
+    /// A declared name that isn't a valid identifier. e.g. `user-id`, `2fa`
+    InvalidIdent,
+    /// A declared name that is a Go keyword. e.g. `type`
+    KeywordIdent,
+    /// A name declared twice in the same scope.
+    Duplicate,
+    /// `break`, `continue` or `fallthrough` where it isn't allowed.
+    MisplacedBranch,
+    /// A function with results whose body doesn't end in a terminating statement.
+    MissingReturn,
+    /// A local variable that is never used.
+    UnusedVar,
+    /// An import of a package of the module, see [`Meta::module_import`], outside of a
+    /// [`Module`].
+    ModuleImport,
+    /// Operands or values of incompatible types, from [`Package::check_types`].
+    TypeMismatch,
+    /// A constant that doesn't fit its type. e.g. `int8(300)`
+    Overflow,
+    /// A value of another numeric type, assignable only with a conversion.
+    NeedsConversion,
+}
+
+/// A problem found by [`Package::validate`] or [`Package::check_types`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// Enclosing nodes, outermost first. e.g. `["func Load", "for", "if"]`
+    pub path: Vec<String>,
+    /// 1-based line in the rendered package.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.line)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join(" > "))?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Package {
+    /// Check the rendered package for mistakes that `go build` would reject: invalid or keyword
+    /// identifiers, duplicate declarations, misplaced `break`/`continue`/`fallthrough`, missing
+    /// returns and unused local variables. Module imports are reported, they are only resolved
+    /// by [`Module::render`].
+    pub fn validate(&self) -> Result<(), Vec<Diagnostic>> {
+        let src = self.to_string();
+        let mut v = Validator::new(tokenize(&src));
+        let line = v.toks.iter().find(|t| t.is("package")).map_or(1, |t| t.line);
+        for dir in &self.module_imports {
+            let msg = format!("import of module package `{dir}` outside of a module");
+            v.diag(DiagnosticKind::ModuleImport, line, msg);
+        }
+        v.file();
+        if v.diags.is_empty() { Ok(()) } else { Err(v.diags) }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Ctx {
+    For,
+    Switch,
+    Select,
+}
+
+struct Breakable {
+    ctx: Ctx,
+    label: Option<String>,
+    broken: bool,
+}
+
+struct Var {
+    line: usize,
+    used: bool,
+    /// Report if unused, false for parameters and constants.
+    track: bool,
+}
+
+#[derive(Default)]
+struct StmtInfo {
+    terminating: bool,
+    fallthrough: bool,
+}
+
+struct Validator {
+    toks: Vec<Token>,
+    pos: usize,
+    diags: Vec<Diagnostic>,
+    path: Vec<String>,
+    scopes: Vec<HashMap<String, Var>>,
+    breakables: Vec<Breakable>,
+    label: Option<String>,
+    globals: HashMap<String, usize>,
+}
+
+impl Walker for Validator {
+    fn toks(&self) -> &[Token] {
+        &self.toks
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn pos_mut(&mut self) -> &mut usize {
+        &mut self.pos
+    }
+}
+
+const GLUE: [&str; 12] = ["-", "+", "@", "$", "#", "?", "!", "~", "%", "^", "&", "\\"];
+const ASSIGN_OPS: [&str; 14] =
+    ["=", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<=", ">>=", "&^=", "++", "--"];
+
+impl Validator {
+    fn new(toks: Vec<Token>) -> Self {
+        Self {
+            toks,
+            pos: 0,
+            diags: Vec::new(),
+            path: Vec::new(),
+            scopes: Vec::new(),
+            breakables: Vec::new(),
+            label: None,
+            globals: HashMap::new(),
+        }
+    }
+
+    fn expect(&mut self, text: &str) -> bool {
+        if self.eat(text) {
+            return true;
+        }
+        let tok = self.peek().clone();
+        self.diag(
+            DiagnosticKind::Syntax,
+            tok.line,
+            format!("expected `{text}`, found `{}`", tok.text),
+        );
+        false
+    }
+
+    fn diag(&mut self, kind: DiagnosticKind, line: usize, message: String) {
+        self.diags.push(Diagnostic { kind, path: self.path.clone(), line, message });
+    }
+
+    /// Read a declared name at `i`, gluing adjacent tokens so that e.g. `user-id` is reported as
+    /// a whole. Returns the name and the index after it.
+    fn glued_name(&self, i: usize) -> (String, usize) {
+        let first = self.tok(i);
+        let (mut name, mut end, mut j) = (first.text.clone(), first.end, i + 1);
+        while let Some(next) = self.toks.get(j) {
+            let glue = matches!(next.kind, Kind::Ident | Kind::Keyword | Kind::Literal)
+                || (next.kind == Kind::Punct && GLUE.contains(&next.text.as_str()));
+            if next.start != end || !glue {
+                break;
+            }
+            name.push_str(&next.text);
+            end = next.end;
+            j += 1;
+        }
+        (name, j)
+    }
+
+    /// Check the name at `i`, returns it and the index after it.
+    fn check_name(&mut self, i: usize) -> Option<(String, usize)> {
+        let tok = self.tok(i).clone();
+        if !matches!(tok.kind, Kind::Ident | Kind::Keyword | Kind::Literal) {
+            let msg = format!("expected identifier, found `{}`", tok.text);
+            self.diag(DiagnosticKind::Syntax, tok.line, msg);
+            return None;
+        }
+        let (name, next) = self.glued_name(i);
+        if tok.kind == Kind::Keyword && name == tok.text {
+            let msg = format!("`{name}` is a keyword and can't be used as an identifier");
+            self.diag(DiagnosticKind::KeywordIdent, tok.line, msg);
+        } else if !is_ident(&name) {
+            self.diag(
+                DiagnosticKind::InvalidIdent,
+                tok.line,
+                format!("invalid identifier `{name}`"),
+            );
+        }
+        Some((name, next))
+    }
+
+    fn name(&mut self) -> Option<(String, usize)> {
+        let line = self.peek().line;
+        let (name, next) = self.check_name(self.pos)?;
+        self.pos = next;
+        Some((name, line))
+    }
+
+    fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap_or_default();
+        let mut unused: Vec<_> = scope.into_iter().filter(|(_, v)| v.track && !v.used).collect();
+        unused.sort_by_key(|(name, v)| (v.line, name.clone()));
+        for (name, var) in unused {
+            self.diag(
+                DiagnosticKind::UnusedVar,
+                var.line,
+                format!("declared and not used: {name}"),
+            );
+        }
+    }
+
+    fn declare(&mut self, name: &str, line: usize, track: bool) {
+        if name == "_" {
+            return;
+        }
+        let scope = self.scopes.last_mut().expect("inside a function");
+        if scope.insert(name.to_string(), Var { line, used: false, track }).is_some() {
+            let msg = format!("`{name}` redeclared in this block");
+            self.diag(DiagnosticKind::Duplicate, line, msg);
+        }
+    }
+
+    fn declare_global(&mut self, key: String, line: usize) {
+        if key == "_" || key == "init" {
+            return;
+        }
+        if let Some(prev) = self.globals.insert(key.clone(), line) {
+            let msg = format!("`{key}` redeclared in this package, previously on line {prev}");
+            self.diag(DiagnosticKind::Duplicate, line, msg);
+        }
+    }
+
+    fn use_var(&mut self, name: &str) {
+        if let Some(var) = self.scopes.iter_mut().rev().find_map(|s| s.get_mut(name)) {
+            var.used = true;
+        }
+    }
+
+    fn skip_type(&mut self) {
+        let end = self.type_end(self.pos);
+        if end == self.pos {
+            let tok = self.peek().clone();
+            self.diag(
+                DiagnosticKind::Syntax,
+                tok.line,
+                format!("expected type, found `{}`", tok.text),
+            );
+            self.bump();
+        }
+        self.pos = end;
+    }
+
+    /// Mark the identifiers in `[from, to)` as used, checking function literals on the way.
+    fn scan(&mut self, from: usize, to: usize) {
+        let mut i = from;
+        while i < to {
+            if self.is_func_lit(i) {
+                self.pos = i;
+                self.func_lit();
+                i = self.pos;
+                continue;
+            }
+            let tok = self.tok(i);
+            if tok.kind == Kind::Ident && !(i > 0 && self.tok(i - 1).is(".")) {
+                let name = tok.text.clone();
+                self.use_var(&name);
+            }
+            i += 1;
+        }
+        self.pos = to;
+    }
+
+    fn scan_until(&mut self, stop: Stop) {
+        let end = self.stmt_end(self.pos, stop);
+        self.scan(self.pos, end);
+    }
+
+    /// Report string and rune literals that end at a newline or the end of the file.
+    fn literals(&mut self) {
+        let unterminated: Vec<_> = self
+            .toks
+            .iter()
+            .filter_map(|tok| {
+                let quote = tok.text.chars().next().filter(|c| matches!(c, '"' | '\'' | '`'))?;
+                let body = tok.text[1..].strip_suffix(quote);
+                let escaped = body.is_some_and(|b| {
+                    quote != '`' && b.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+                });
+                (tok.kind == Kind::Literal && (body.is_none() || escaped)).then_some(tok.line)
+            })
+            .collect();
+        for line in unterminated {
+            self.diag(DiagnosticKind::Syntax, line, "string literal not terminated".into());
+        }
+    }
+
+    fn file(&mut self) {
+        self.literals();
+        self.skip_semis();
+        if self.eat("package") {
+            self.name();
+        } else {
+            let line = self.peek().line;
+            self.diag(DiagnosticKind::Syntax, line, "missing package clause".into());
+        }
+        loop {
+            self.skip_semis();
+            let tok = self.peek().clone();
+            match tok.text.as_str() {
+                _ if tok.kind == Kind::Eof => break,
+                "import" => {
+                    self.bump();
+                    if self.peek().is("(") {
+                        self.pos = self.balanced_end(self.pos);
+                    } else {
+                        self.pos = self.stmt_end(self.pos, Stop::Stmt);
+                    }
+                }
+                "var" | "const" | "type" => {
+                    self.bump();
+                    self.group(&tok.text, true);
+                }
+                "func" => {
+                    self.bump();
+                    self.func_decl();
+                }
+                _ => {
+                    let msg = format!("unexpected `{}` at top level", tok.text);
+                    self.diag(DiagnosticKind::Syntax, tok.line, msg);
+                    self.bump();
+                    self.pos = self.stmt_end(self.pos, Stop::Stmt);
+                }
+            }
+        }
+    }
+
+    /// Specs of a `var`, `const` or `type` declaration, grouped or not.
+    fn group(&mut self, kw: &str, global: bool) {
+        self.specs(|v| v.spec(kw, global));
+    }
+
+    fn spec(&mut self, kw: &str, global: bool) {
+        let mut names = Vec::new();
+        if kw == "type" {
+            names.extend(self.name());
+            // type parameters, unless it's an array length. e.g. `type A [N]int`
+            if self.peek().is("[")
+                && self.tok(self.pos + 1).kind == Kind::Ident
+                && !self.tok(self.pos + 2).is("]")
+            {
+                self.pos = self.balanced_end(self.pos);
+            }
+            self.eat("=");
+            if self.peek().is("struct") {
+                self.struct_fields(self.pos + 1);
+            }
+            self.skip_type();
+        } else {
+            names.extend(self.name());
+            while self.eat(",") {
+                names.extend(self.name());
+            }
+            let tok = self.peek();
+            if !tok.is("=") && tok.kind != Kind::Semi && !tok.is(")") {
+                self.skip_type();
+            }
+            if self.eat("=") {
+                self.scan_until(Stop::Stmt);
+            }
+        }
+        for (name, line) in names {
+            if global {
+                self.declare_global(name, line);
+            } else {
+                self.declare(&name, line, kw == "var");
+            }
+        }
+    }
+
+    /// Check the field names of the struct body opened at `i`.
+    fn struct_fields(&mut self, i: usize) {
+        if !self.tok(i).is("{") {
+            return;
+        }
+        let end = self.balanced_end(i) - 1;
+        let mut j = i + 1;
+        while j < end {
+            let tok = self.tok(j).clone();
+            if tok.kind == Kind::Semi || tok.is(",") {
+                j += 1;
+                continue;
+            }
+            let field_end = self.stmt_end(j, Stop::Stmt).min(end);
+            let (_, after) = self.glued_name(j);
+            let next = self.tok(after);
+            // embedded fields are a lone, possibly qualified, type.
+            let embedded = tok.is("*")
+                || after >= field_end
+                || next.is(".")
+                || (next.kind == Kind::Literal && after + 1 >= field_end);
+            if !embedded {
+                for (from, to) in self.split_commas(j, self.type_start(j, field_end)) {
+                    if from < to {
+                        self.check_name(from);
+                    }
+                }
+            }
+            j = field_end.max(j + 1);
+        }
+    }
+
+    /// Start of the type in a field or parameter declaration `a, b T` spanning `[from, to)`.
+    fn type_start(&self, from: usize, to: usize) -> usize {
+        let mut i = from;
+        loop {
+            let (_, after) = self.glued_name(i);
+            if after < to && self.tok(after).is(",") {
+                i = after + 1;
+            } else {
+                return after.min(to);
+            }
+        }
+    }
+
+    fn func_decl(&mut self) {
+        let line = self.peek().line;
+        self.push_scope();
+        let mut recv_ty = None;
+        if self.peek().is("(") {
+            let end = self.balanced_end(self.pos) - 1;
+            let start = self.pos + 1;
+            let named = end > start + 1 && !self.tok(start + 1).is(".");
+            if named && let Some((name, line)) = self.check_name(start).map(|(n, _)| (n, line)) {
+                self.declare(&name, line, false);
+            }
+            recv_ty = (start..end)
+                .map(|i| self.tok(i))
+                .take_while(|t| !t.is("["))
+                .filter(|t| t.kind == Kind::Ident)
+                .last()
+                .map(|t| t.text.clone());
+            self.pos = end + 1;
+        }
+        let Some((name, _)) = self.name() else {
+            self.pop_scope();
+            self.pos = self.stmt_end(self.pos, Stop::Stmt);
+            return;
+        };
+        match &recv_ty {
+            Some(ty) => {
+                self.declare_global(format!("{ty}.{name}"), line);
+                self.path.push(format!("method {ty}.{name}"));
+            }
+            None => {
+                self.declare_global(name.clone(), line);
+                self.path.push(format!("func {name}"));
+            }
+        }
+        if self.peek().is("[") {
+            self.pos = self.balanced_end(self.pos);
+        }
+        self.func_rest();
+        self.pop_scope();
+        self.path.pop();
+    }
+
+    fn func_lit(&mut self) {
+        self.bump();
+        self.path.push("func literal".into());
+        self.push_scope();
+        self.func_rest();
+        self.pop_scope();
+        self.path.pop();
+    }
+
+    /// Signature and body, parameters go in the current scope.
+    fn func_rest(&mut self) {
+        self.params();
+        let results = if self.peek().is("(") {
+            self.params() > 0
+        } else if !self.peek().is("{") && starts_type(self.peek()) {
+            self.skip_type();
+            true
+        } else {
+            false
+        };
+        if !self.peek().is("{") {
+            return;
+        }
+        let breakables = std::mem::take(&mut self.breakables);
+        self.bump();
+        let info = self.stmt_list(false);
+        let line = self.peek().line;
+        self.expect("}");
+        self.breakables = breakables;
+        if results && !info.terminating {
+            self.diag(DiagnosticKind::MissingReturn, line, "missing return".into());
+        }
+    }
+
+    /// A parameter list, declaring named parameters. Returns the number of entries.
+    fn params(&mut self) -> usize {
+        if !self.peek().is("(") {
+            self.expect("(");
+            return 0;
+        }
+        let end = self.balanced_end(self.pos) - 1;
+        let elems = self.split_commas(self.pos + 1, end);
+        let named = |v: &Self, (from, to): (usize, usize)| {
+            let (_, after) = v.glued_name(from);
+            let next = v.tok(after);
+            matches!(v.tok(from).kind, Kind::Ident | Kind::Keyword | Kind::Literal)
+                && after < to
+                && !next.is(".")
+                && !(next.is("[") && v.balanced_end(after) == to)
+        };
+        if elems.iter().any(|e| named(self, *e)) {
+            let mut seen = Vec::new();
+            for (from, _) in elems.iter().copied() {
+                let line = self.tok(from).line;
+                if let Some((name, _)) = self.check_name(from) {
+                    if name != "_" && seen.contains(&name) {
+                        let msg = format!("duplicate argument `{name}`");
+                        self.diag(DiagnosticKind::Duplicate, line, msg);
+                    }
+                    self.declare(&name, line, false);
+                    seen.push(name);
+                }
+            }
+        }
+        self.pos = end + 1;
+        elems.len()
+    }
+
+    fn block(&mut self) -> StmtInfo {
+        self.expect("{");
+        self.push_scope();
+        let info = self.stmt_list(false);
+        self.pop_scope();
+        self.expect("}");
+        info
+    }
+
+    /// Statements up to the closing `}` or next clause. Returns the info of the last one.
+    fn stmt_list(&mut self, in_case: bool) -> StmtInfo {
+        let (mut last, mut last_line) = (StmtInfo::default(), 0);
+        loop {
+            self.skip_semis();
+            let tok = self.peek();
+            if tok.kind == Kind::Eof || tok.is("}") || tok.is("case") || tok.is("default") {
+                break;
+            }
+            let line = tok.line;
+            // only the last statement of a clause may fall through.
+            if last.fallthrough {
+                self.misplaced_fallthrough(last_line);
+            }
+            let pos = self.pos;
+            last = self.stmt();
+            last_line = line;
+            self.end_stmt(pos);
+            if last.fallthrough && !in_case {
+                self.misplaced_fallthrough(line);
+                last.fallthrough = false;
+            }
+        }
+        last
+    }
+
+    fn misplaced_fallthrough(&mut self, line: usize) {
+        let msg = "fallthrough statement out of place".into();
+        self.diag(DiagnosticKind::MisplacedBranch, line, msg);
+    }
+
+    /// Consume the statement terminator, recovering from junk.
+    fn end_stmt(&mut self, start: usize) {
+        let tok = self.peek().clone();
+        if tok.kind == Kind::Semi {
+            self.bump();
+        } else if !(tok.kind == Kind::Eof || tok.is("}") || tok.is("case") || tok.is("default")) {
+            let msg = format!("unexpected `{}` at end of statement", tok.text);
+            self.diag(DiagnosticKind::Syntax, tok.line, msg);
+            self.pos = self.stmt_end(self.pos.max(start + 1), Stop::Stmt);
+        }
+    }
+
+    fn stmt(&mut self) -> StmtInfo {
+        let tok = self.peek().clone();
+        let mut info = StmtInfo::default();
+        match tok.text.as_str() {
+            _ if tok.kind == Kind::Ident && self.tok(self.pos + 1).is(":") => {
+                let (label, _) = self.name().unwrap_or_default();
+                self.bump();
+                self.skip_semis();
+                self.label = Some(label);
+                info = self.stmt();
+                self.label = None;
+            }
+            "{" if tok.kind == Kind::Punct => info = self.block(),
+            "var" | "const" | "type" if tok.kind == Kind::Keyword => {
+                self.bump();
+                self.group(&tok.text, false);
+            }
+            "return" | "go" | "defer" if tok.kind == Kind::Keyword => {
+                self.bump();
+                self.scan_until(Stop::Stmt);
+                info.terminating = tok.text == "return";
+            }
+            "break" | "continue" if tok.kind == Kind::Keyword => {
+                self.bump();
+                let label = (self.peek().kind == Kind::Ident).then(|| self.bump().text);
+                self.branch(&tok, label);
+            }
+            "goto" if tok.kind == Kind::Keyword => {
+                self.bump();
+                self.bump();
+                info.terminating = true;
+            }
+            "fallthrough" if tok.kind == Kind::Keyword => {
+                self.bump();
+                info.fallthrough = true;
+            }
+            "if" if tok.kind == Kind::Keyword => info.terminating = self.if_stmt(),
+            "for" if tok.kind == Kind::Keyword => info.terminating = self.for_stmt(),
+            "switch" if tok.kind == Kind::Keyword => info.terminating = self.switch_stmt(),
+            "select" if tok.kind == Kind::Keyword => info.terminating = self.select_stmt(),
+            _ => info = self.simple_stmt(Stop::Stmt),
+        }
+        info
+    }
+
+    fn branch(&mut self, tok: &Token, label: Option<String>) {
+        let kw = tok.text.as_str();
+        let target = self.breakables.iter_mut().rev().find(|b| match &label {
+            Some(label) => b.label.as_ref() == Some(label),
+            None => kw == "break" || b.ctx == Ctx::For,
+        });
+        match target {
+            Some(target) if kw == "continue" && target.ctx != Ctx::For => {
+                let msg = format!("invalid continue label {}", label.unwrap_or_default());
+                self.diag(DiagnosticKind::MisplacedBranch, tok.line, msg);
+            }
+            Some(target) => target.broken |= kw == "break",
+            None => {
+                let msg = match (kw, label) {
+                    (_, Some(label)) => format!("invalid {kw} label {label}"),
+                    ("break", None) => "break is not in a loop, switch, or select".into(),
+                    _ => "continue is not in a loop".into(),
+                };
+                self.diag(DiagnosticKind::MisplacedBranch, tok.line, msg);
+            }
+        }
+    }
+
+    fn simple_stmt(&mut self, stop: Stop) -> StmtInfo {
+        let start = self.pos;
+        let end = self.stmt_end(start, stop);
+        let mut info = StmtInfo::default();
+        let mut op = None;
+        let mut i = start;
+        while i < end {
+            let tok = self.tok(i);
+            if tok.kind == Kind::Punct {
+                let text = tok.text.as_str();
+                if text == ":=" || ASSIGN_OPS.contains(&text) || (text == "<-" && i > start) {
+                    op = Some((i, tok.text.clone()));
+                    break;
+                }
+                if ["(", "[", "{"].contains(&text) {
+                    i = self.balanced_end(i);
+                    continue;
+                }
+            }
+            if self.is_func_lit(i) {
+                i = self.func_lit_end(i);
+                continue;
+            }
+            i += 1;
+        }
+        let Some((op_at, op)) = op else {
+            let first = self.tok(start);
+            info.terminating = first.kind == Kind::Ident
+                && first.text == "panic"
+                && self.tok(start + 1).is("(")
+                && self.balanced_end(start + 1) == end;
+            self.scan(start, end);
+            return info;
+        };
+        let rhs = op_at + 1;
+        let lhs = self.split_commas(start, op_at);
+        if op == ":=" {
+            self.scan(rhs, end);
+            let mut new = false;
+            for (from, _) in lhs.iter().copied() {
+                let line = self.tok(from).line;
+                let Some((name, _)) = self.check_name(from) else { continue };
+                let declared = self.scopes.last().is_some_and(|s| s.contains_key(&name));
+                if name != "_" && !declared {
+                    self.declare(&name, line, true);
+                    new = true;
+                }
+            }
+            if !new {
+                let line = self.tok(op_at).line;
+                let msg = "no new variables on left side of :=".into();
+                self.diag(DiagnosticKind::Duplicate, line, msg);
+            }
+        } else {
+            for (from, to) in lhs {
+                // assigning to a plain variable isn't a use.
+                if op == "<-" || to - from > 1 {
+                    self.scan(from, to);
+                }
+            }
+            self.scan(rhs, end);
+        }
+        self.pos = end;
+        info
+    }
+
+    fn if_stmt(&mut self) -> bool {
+        self.bump();
+        self.path.push("if".into());
+        self.push_scope();
+        self.simple_stmt(Stop::Header);
+        if self.eat(";") {
+            self.simple_stmt(Stop::Header);
+        }
+        let then = self.block().terminating;
+        let mut terminating = false;
+        if self.eat("else") {
+            terminating =
+                if self.peek().is("if") { self.if_stmt() } else { self.block().terminating };
+            terminating &= then;
+        }
+        self.pop_scope();
+        self.path.pop();
+        terminating
+    }
+
+    fn for_stmt(&mut self) -> bool {
+        self.bump();
+        let label = self.label.take();
+        self.path.push("for".into());
+        self.push_scope();
+        let mut infinite = true;
+        if !self.peek().is("{") {
+            self.simple_stmt(Stop::Header);
+            if self.eat(";") {
+                if !self.peek().is(";") {
+                    self.simple_stmt(Stop::Header);
+                    infinite = false;
+                }
+                self.expect(";");
+                if !self.peek().is("{") {
+                    self.simple_stmt(Stop::Header);
+                }
+            } else {
+                infinite = false;
+            }
+        }
+        self.breakables.push(Breakable { ctx: Ctx::For, label, broken: false });
+        self.block();
+        let broken = self.breakables.pop().is_some_and(|b| b.broken);
+        self.pop_scope();
+        self.path.pop();
+        infinite && !broken
+    }
+
+    fn switch_stmt(&mut self) -> bool {
+        self.bump();
+        let label = self.label.take();
+        self.path.push("switch".into());
+        self.push_scope();
+        let header_end = self.stmt_end(self.pos, Stop::Header);
+        let header_end = if self.tok(header_end).is(";") {
+            self.stmt_end(header_end + 1, Stop::Header)
+        } else {
+            header_end
+        };
+        let is_type = (self.pos..header_end)
+            .any(|i| self.tok(i).is(".") && self.tok(i + 1).is("(") && self.tok(i + 2).is("type"));
+        if !self.peek().is("{") {
+            self.simple_stmt(Stop::Header);
+            if self.eat(";") && !self.peek().is("{") {
+                self.simple_stmt(Stop::Header);
+            }
+        }
+        self.breakables.push(Breakable { ctx: Ctx::Switch, label, broken: false });
+        let clauses = self.clauses(false);
+        let broken = self.breakables.pop().is_some_and(|b| b.broken);
+        let mut terminating = !broken && clauses.iter().any(|(_, default, _)| *default);
+        let last = clauses.len().saturating_sub(1);
+        for (i, (info, _, line)) in clauses.iter().enumerate() {
+            let msg = match (info.fallthrough, is_type, i == last) {
+                (true, true, _) => "cannot fallthrough in type switch",
+                (true, false, true) => "cannot fallthrough final case in switch",
+                _ => "",
+            };
+            if !msg.is_empty() {
+                self.diag(DiagnosticKind::MisplacedBranch, *line, msg.into());
+            }
+            terminating &= info.terminating || info.fallthrough;
+        }
+        self.pop_scope();
+        self.path.pop();
+        terminating
+    }
+
+    fn select_stmt(&mut self) -> bool {
+        self.bump();
+        let label = self.label.take();
+        self.path.push("select".into());
+        self.breakables.push(Breakable { ctx: Ctx::Select, label, broken: false });
+        let clauses = self.clauses(true);
+        let broken = self.breakables.pop().is_some_and(|b| b.broken);
+        for (info, _, line) in &clauses {
+            if info.fallthrough {
+                self.misplaced_fallthrough(*line);
+            }
+        }
+        self.path.pop();
+        !broken && clauses.iter().all(|(info, ..)| info.terminating)
+    }
+
+    /// Clauses of a switch or select. Returns the info of each clause's last statement, whether
+    /// it's the default clause and the line of that statement.
+    fn clauses(&mut self, select: bool) -> Vec<(StmtInfo, bool, usize)> {
+        let mut clauses = Vec::new();
+        self.expect("{");
+        loop {
+            self.skip_semis();
+            let tok = self.peek().clone();
+            if tok.kind == Kind::Eof || tok.is("}") {
+                break;
+            }
+            self.push_scope();
+            let default = tok.is("default");
+            if tok.is("case") || default {
+                self.bump();
+                if !default {
+                    if select {
+                        self.simple_stmt(Stop::Colon);
+                    } else {
+                        self.scan_until(Stop::Colon);
+                    }
+                }
+                self.expect(":");
+            } else {
+                let msg = format!("expected `case` or `default`, found `{}`", tok.text);
+                self.diag(DiagnosticKind::Syntax, tok.line, msg);
+                self.bump();
+            }
+            let name =
+                if default { "default".into() } else { format!("case {}", clauses.len() + 1) };
+            self.path.push(name);
+            let info = self.stmt_list(true);
+            let line = self.tok(self.pos.saturating_sub(1)).line;
+            self.path.pop();
+            self.pop_scope();
+            clauses.push((info, default, line));
+        }
+        self.expect("}");
+        clauses
+    }
+}
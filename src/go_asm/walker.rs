@@ -0,0 +1,226 @@
+//! Token navigation shared by the validator and the type checker, which walk the same
+//! [tokens](super::lexer::tokenize) of a rendered [`Package`](super::Package).
+
+use super::lexer::{Kind, Token};
+
+/// Where a statement or expression ends.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Stop {
+    Stmt,
+    /// Header of `if`, `for` and `switch`, ending at `{` or `;`.
+    Header,
+    /// `case` of a select, ending at `:`.
+    Colon,
+}
+
+/// Whether a type can start at `tok`.
+pub(crate) fn starts_type(tok: &Token) -> bool {
+    tok.kind == Kind::Ident
+        || ["*", "[", "(", "<-", "map", "chan", "func", "struct", "interface"]
+            .iter()
+            .any(|t| tok.is(t))
+}
+
+/// A cursor over the tokens of a file, ending with [`Kind::Eof`].
+pub(crate) trait Walker {
+    fn toks(&self) -> &[Token];
+
+    fn pos(&self) -> usize;
+
+    fn pos_mut(&mut self) -> &mut usize;
+
+    fn tok(&self, i: usize) -> &Token {
+        let toks = self.toks();
+        &toks[i.min(toks.len() - 1)]
+    }
+
+    fn peek(&self) -> &Token {
+        self.tok(self.pos())
+    }
+
+    fn bump(&mut self) -> Token {
+        let tok = self.peek().clone();
+        if tok.kind != Kind::Eof {
+            *self.pos_mut() += 1;
+        }
+        tok
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        let eaten = self.peek().is(text);
+        if eaten {
+            *self.pos_mut() += 1;
+        }
+        eaten
+    }
+
+    fn skip_semis(&mut self) {
+        while self.peek().kind == Kind::Semi {
+            *self.pos_mut() += 1;
+        }
+    }
+
+    /// Walk the specs of a `var`, `const` or `type` declaration, grouped in parentheses or not.
+    fn specs(&mut self, mut spec: impl FnMut(&mut Self))
+    where
+        Self: Sized,
+    {
+        if !self.eat("(") {
+            spec(self);
+            return;
+        }
+        loop {
+            self.skip_semis();
+            if self.eat(")") || self.peek().kind == Kind::Eof {
+                return;
+            }
+            let pos = self.pos();
+            spec(self);
+            if self.pos() == pos {
+                self.bump();
+            }
+        }
+    }
+
+    /// Index after the bracket group opened at `i`, or after `i` if it doesn't open one.
+    fn balanced_end(&self, i: usize) -> usize {
+        let mut depth = 0;
+        let mut j = i;
+        loop {
+            let tok = self.tok(j);
+            if tok.kind == Kind::Eof {
+                return j;
+            }
+            if tok.kind == Kind::Punct {
+                match tok.text.as_str() {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" => depth -= 1,
+                    _ => {}
+                }
+            }
+            j += 1;
+            if depth <= 0 {
+                return j;
+            }
+        }
+    }
+
+    /// Index after the type starting at `i`.
+    fn type_end(&self, mut i: usize) -> usize {
+        loop {
+            let tok = self.tok(i);
+            match tok.text.as_str() {
+                "*" | "..." | "<-" | "chan" => i += 1,
+                "[" => i = self.balanced_end(i),
+                "map" => i = self.balanced_end(i + 1),
+                "struct" | "interface" if self.tok(i + 1).is("{") => {
+                    return self.balanced_end(i + 1);
+                }
+                "func" => {
+                    i = self.balanced_end(i + 1);
+                    let next = self.tok(i);
+                    if next.is("(") {
+                        return self.balanced_end(i);
+                    }
+                    if starts_type(next) {
+                        return self.type_end(i);
+                    }
+                    return i;
+                }
+                "(" => return self.balanced_end(i),
+                _ if tok.kind == Kind::Ident => {
+                    i += 1;
+                    if self.tok(i).is(".") {
+                        i += 2;
+                    }
+                    if self.tok(i).is("[") {
+                        i = self.balanced_end(i);
+                    }
+                    return i;
+                }
+                _ => return i,
+            }
+        }
+    }
+
+    fn is_func_lit(&self, i: usize) -> bool {
+        self.tok(i).is("func") && self.tok(i + 1).is("(")
+    }
+
+    /// Index after the function literal starting at `i`.
+    fn func_lit_end(&self, i: usize) -> usize {
+        let mut j = self.balanced_end(i + 1);
+        if self.tok(j).is("(") {
+            j = self.balanced_end(j);
+        } else if starts_type(self.tok(j)) {
+            j = self.type_end(j);
+        }
+        if self.tok(j).is("{") { self.balanced_end(j) } else { j }
+    }
+
+    /// Index of the token ending the statement or expression starting at `i`.
+    fn stmt_end(&self, mut i: usize, stop: Stop) -> usize {
+        let mut depth = 0;
+        loop {
+            let tok = self.tok(i);
+            if tok.kind == Kind::Eof {
+                return i;
+            }
+            if depth == 0
+                && (tok.kind == Kind::Semi
+                    || (stop == Stop::Header && tok.is("{"))
+                    || (stop == Stop::Colon && tok.is(":")))
+            {
+                return i;
+            }
+            if self.is_func_lit(i) {
+                i = self.func_lit_end(i);
+                continue;
+            }
+            if tok.kind == Kind::Punct {
+                match tok.text.as_str() {
+                    "(" | "[" | "{" => depth += 1,
+                    ")" | "]" | "}" if depth == 0 => return i,
+                    ")" | "]" | "}" => depth -= 1,
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+    }
+
+    /// Index of the `{` opening the body of the header starting at `i`, past its `;`.
+    fn header_end(&self, mut i: usize) -> usize {
+        loop {
+            i = self.stmt_end(i, Stop::Header);
+            if self.tok(i).kind != Kind::Semi {
+                return i;
+            }
+            i += 1;
+        }
+    }
+
+    /// Split `[from, to)` on top level commas.
+    fn split_commas(&self, from: usize, to: usize) -> Vec<(usize, usize)> {
+        let mut res = Vec::new();
+        let (mut start, mut i) = (from, from);
+        while i < to {
+            let tok = self.tok(i);
+            if tok.is(",") {
+                res.push((start, i));
+                start = i + 1;
+                i += 1;
+            } else if matches!(tok.text.as_str(), "(" | "[" | "{") && tok.kind == Kind::Punct {
+                i = self.balanced_end(i);
+            } else if self.is_func_lit(i) {
+                i = self.func_lit_end(i);
+            } else {
+                i += 1;
+            }
+        }
+        if start < to {
+            res.push((start, to));
+        }
+        res
+    }
+}
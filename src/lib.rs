@@ -3,3 +3,6 @@ pub mod go_asm;
 
 #[cfg_attr(feature = "py", path = "py_asm/mod.rs")]
 pub mod py_asm;
+
+pub mod naming;
+pub mod output;
@@ -0,0 +1,178 @@
+//! Language independent name helpers, see `go_asm::naming` and `py_asm::naming` for the rules of
+//! each language.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+
+/// Split a name into words on separators, invalid characters and case changes. Digits stay in
+/// their word. e.g. `HTTPServer` -> `["HTTP", "Server"]`, `user-id` -> `["user", "id"]`
+pub fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !word.is_empty() {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Uppercase the first letter and lowercase the rest. e.g. `hTTP` -> `Http`
+pub fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or(String::new(), |c| {
+        c.to_uppercase().chain(chars.flat_map(char::to_lowercase)).collect()
+    })
+}
+
+/// e.g. `HTTPServer` -> `http_server`
+pub fn snake_case(name: &str) -> String {
+    words(name).iter().map(|w| w.to_lowercase()).collect::<Vec<_>>().join("_")
+}
+
+/// e.g. `http_server` -> `HTTP_SERVER`
+pub fn upper_snake_case(name: &str) -> String {
+    words(name).iter().map(|w| w.to_uppercase()).collect::<Vec<_>>().join("_")
+}
+
+/// e.g. `user-id` -> `UserId`
+pub fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|w| capitalize(w)).collect()
+}
+
+/// e.g. `user-id` -> `userId`
+pub fn camel_case(name: &str) -> String {
+    let words = words(name);
+    let mut words = words.iter();
+    let first = words.next().map_or(String::new(), |w| w.to_lowercase());
+    first + &words.map(|w| capitalize(w)).collect::<String>()
+}
+
+/// `base`, or the first of `base_1`, `base_2`, ... that isn't `taken`.
+pub fn unique(base: impl Display, taken: impl Fn(&str) -> bool) -> String {
+    let base = base.to_string();
+    (0..)
+        .map(|i| if i == 0 { base.clone() } else { format!("{base}_{i}") })
+        .find(|name| !taken(name))
+        .unwrap()
+}
+
+/// Names a node binds in the scope it is pushed into.
+pub trait Bindings {
+    fn bindings(&self) -> Vec<String>;
+}
+
+/// A binding that hides one of an enclosing scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shadowing {
+    pub name: String,
+    /// Depth of the hiding and the hidden scope, 0 for the outermost.
+    pub depth: usize,
+    pub outer: usize,
+}
+
+impl Display for Shadowing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` shadows a binding of an enclosing scope", self.name)
+    }
+}
+
+/// Bindings visible where code is being generated, to pick temporaries that don't collide with
+/// them. Enter a scope wherever the language opens one: every block in Go, every function or
+/// class body in Python.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    scopes: Vec<HashSet<String>>,
+    shadowing: Vec<Shadowing>,
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self { scopes: vec![HashSet::new()], shadowing: Vec::new() }
+    }
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enter(&mut self) -> &mut Self {
+        self.scopes.push(HashSet::new());
+        self
+    }
+
+    /// Enter a scope that starts with bindings, e.g. parameters or loop variables.
+    pub fn enter_with(&mut self, node: &impl Bindings) -> &mut Self {
+        self.enter();
+        for name in node.bindings() {
+            self.bind(name);
+        }
+        self
+    }
+
+    /// Leave the innermost scope, the outermost one is never left.
+    pub fn leave(&mut self) -> &mut Self {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+        self
+    }
+
+    /// Bind `name` in the innermost scope, `_` binds nothing.
+    pub fn bind(&mut self, name: impl Display) -> &mut Self {
+        let name = name.to_string();
+        if name == "_" || name.is_empty() {
+            return self;
+        }
+        let depth = self.scopes.len() - 1;
+        if let Some(outer) = self.scopes[..depth].iter().rposition(|s| s.contains(&name))
+            && !self.scopes[depth].contains(&name)
+        {
+            self.shadowing.push(Shadowing { name: name.clone(), depth, outer });
+        }
+        self.scopes[depth].insert(name);
+        self
+    }
+
+    /// Bind the names `node` introduces and hand it back. e.g. `block.push(scope.track(stmt))`
+    pub fn track<T: Bindings>(&mut self, node: T) -> T {
+        for name in node.bindings() {
+            self.bind(name);
+        }
+        node
+    }
+
+    /// Whether `name` is bound in the innermost or an enclosing scope.
+    pub fn is_bound(&self, name: &str) -> bool {
+        self.scopes.iter().any(|s| s.contains(name))
+    }
+
+    /// Bind and return `base`, or its first suffixed variant not bound in any enclosing scope.
+    /// e.g. `tmp`, `tmp_1`, ...
+    pub fn fresh_name(&mut self, base: impl Display) -> String {
+        let name = unique(base, |name| self.is_bound(name));
+        self.bind(&name);
+        name
+    }
+
+    /// Bindings so far that hide one of an enclosing scope.
+    pub fn shadowing(&self) -> &[Shadowing] {
+        &self.shadowing
+    }
+}
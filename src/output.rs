@@ -0,0 +1,631 @@
+//! Writing generated file trees, shared by `go_asm::Module` and `py_asm::Package`.
+//!
+//! Both write a manifest of the generated files and their content hashes to the output
+//! directory, so that a later [`sync`](crate::go_asm::Module::sync) can tell generated files
+//! that are no longer produced from files added by hand.
+//!
+//! Saving over an existing file keeps its hand-written regions, delimited by markers in the
+//! comment syntax of the language:
+//! ```go
+//! // codeasm:begin custom
+//! func helper() {}
+//! // codeasm:end
+//! ```
+//! The generated code decides where regions are and their default content, the existing file
+//! what is in them. Regenerating without a region of the existing file is an error, so that
+//! hand-written code is never dropped silently.
+//!
+//! A [`Header`] marks a file as generated, in the form Go tooling recognizes.
+//!
+//! Nodes given an origin, e.g. with `Stmt::origin`, carry it as invisible markers through the
+//! rendering. They are stripped from the output and collected into a [`SourceMap`].
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Write};
+use std::path::{Component, Path};
+
+/// Name of the manifest in the output directory.
+pub const MANIFEST: &str = ".codeasm-manifest";
+
+/// Files touched by a sync, by path relative to the output directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SaveReport {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Files of the previous manifest that are no longer generated and were deleted by pruning,
+    /// or by hand.
+    pub removed: Vec<String>,
+    /// Files of the previous manifest that are no longer generated, kept without pruning.
+    pub stale: Vec<String>,
+    /// Files of the previous manifest that are no longer generated but were edited since, never
+    /// deleted.
+    pub edited: Vec<String>,
+}
+
+impl SaveReport {
+    /// Whether any file was written or deleted.
+    pub fn is_modified(&self) -> bool {
+        !(self.added.is_empty() && self.changed.is_empty() && self.removed.is_empty())
+    }
+}
+
+/// Comment at the top of a generated file. e.g.
+/// ```go
+/// // Copyright 2024 Acme Inc.
+///
+/// // Code generated by sdkgen v1.4.0 from api.json. DO NOT EDIT.
+/// // Input hash: fnv1a:8c2a6f0b3e1d9a47
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Header {
+    /// Name of the generator. e.g. `sdkgen`
+    pub generator: String,
+    pub version: String,
+    /// License text, one comment line per line, above the generated notice.
+    pub license: String,
+    /// Name of the generator input and the FNV-1a hash of its content.
+    pub input: Option<(String, u64)>,
+}
+
+impl Header {
+    pub fn new(generator: impl Display) -> Self {
+        Self { generator: generator.to_string(), ..Default::default() }
+    }
+
+    pub fn version(self, version: impl Display) -> Self {
+        Self { version: version.to_string(), ..self }
+    }
+
+    pub fn license(self, license: impl Display) -> Self {
+        Self { license: license.to_string(), ..self }
+    }
+
+    /// Record the input the file is generated from, so that a stale file can be told by
+    /// comparing [`Header::input_hash`] with the hash of the current input.
+    pub fn input(self, name: impl Display, content: &str) -> Self {
+        Self { input: Some((name.to_string(), hash(content))), ..self }
+    }
+
+    /// The input hash recorded in the header of `src`, if any.
+    pub fn input_hash(src: &str) -> Option<u64> {
+        let line = src.lines().find_map(|line| line.split_once("Input hash: fnv1a:"))?;
+        u64::from_str_radix(line.1.trim(), 16).ok()
+    }
+
+    /// Whether `src` was generated from `content`, as recorded by its header.
+    pub fn is_current(src: &str, content: &str) -> bool {
+        Self::input_hash(src) == Some(hash(content))
+    }
+
+    /// The header commented with `comment`, followed by a blank line so that Go doesn't take it
+    /// for the package doc.
+    pub(crate) fn render(&self, comment: &str) -> String {
+        let mut res = String::new();
+        for line in self.license.lines() {
+            writeln!(res, "{}", format!("{comment} {line}").trim_end()).unwrap();
+        }
+        if !res.is_empty() {
+            res.push('\n');
+        }
+        let mut by = self.generator.clone();
+        if !self.version.is_empty() {
+            write!(by, " {}", self.version).unwrap();
+        }
+        if let Some((name, _)) = &self.input {
+            write!(by, " from {name}").unwrap();
+        }
+        writeln!(res, "{comment} Code generated by {by}. DO NOT EDIT.").unwrap();
+        if let Some((_, hash)) = &self.input {
+            writeln!(res, "{comment} Input hash: fnv1a:{hash:016x}").unwrap();
+        }
+        res.push('\n');
+        res
+    }
+}
+
+/// Opens an origin, followed by its text and [`ORIGIN_END`].
+const ORIGIN: char = '\u{E000}';
+const ORIGIN_END: char = '\u{E001}';
+/// Closes the innermost origin.
+const CLOSE: char = '\u{E002}';
+
+/// `node` marked as produced from `origin`. e.g. `schema.json#/definitions/User`
+pub(crate) fn with_origin(origin: impl Display, node: &str) -> String {
+    let origin = origin.to_string();
+    let origin = origin.replace(|c: char| c.is_control() || matches!(c, ORIGIN..=CLOSE), " ");
+    // closed before the trailing newlines, which would leave the marker alone on a line
+    let code = node.trim_end_matches('\n');
+    format!("{ORIGIN}{origin}{ORIGIN_END}{code}{CLOSE}{}", &node[code.len()..])
+}
+
+/// Write the code of a node: without its origin markers, unless formatted with `{:#}` as when
+/// it is embedded into another node, so that the markers reach the enclosing file.
+pub(crate) fn fmt_node(f: &mut std::fmt::Formatter<'_>, src: &str) -> std::fmt::Result {
+    if f.alternate() || !src.contains(ORIGIN) {
+        f.write_str(src)
+    } else {
+        f.write_str(&strip_origins(src))
+    }
+}
+
+/// `src` without its origin markers.
+pub(crate) fn strip_origins(src: &str) -> String {
+    if src.contains(ORIGIN) { source_map("", src).0 } else { src.to_string() }
+}
+
+/// Where the lines of a generated file come from, by the origins given to its nodes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Name of the generated file.
+    pub file: String,
+    /// Distinct origins, in order of first use.
+    pub sources: Vec<String>,
+    /// Segments of every generated line: the column where a segment starts and the index of
+    /// its origin in `sources`, none for code without origin. Columns count UTF-16 code units,
+    /// as in the Source Map v3 spec.
+    pub lines: Vec<Vec<(usize, Option<usize>)>>,
+}
+
+impl SourceMap {
+    /// Origin of the code at `line` and `column`, both 0-based.
+    pub fn origin(&self, line: usize, column: usize) -> Option<&str> {
+        let segments = self.lines.get(line)?;
+        let (_, source) = segments.iter().rev().find(|(col, _)| *col <= column)?;
+        source.map(|i| self.sources[i].as_str())
+    }
+
+    /// The map in the Source Map v3 JSON format, every origin being a source whose mapped
+    /// position is its start.
+    pub fn to_json(&self) -> String {
+        let mut mappings = String::new();
+        let mut prev_source = 0;
+        for (i, segments) in self.lines.iter().enumerate() {
+            if i > 0 {
+                mappings.push(';');
+            }
+            let mut prev_col = 0;
+            for (j, (col, source)) in segments.iter().enumerate() {
+                if j > 0 {
+                    mappings.push(',');
+                }
+                vlq(&mut mappings, *col as i64 - prev_col as i64);
+                prev_col = *col;
+                if let Some(source) = source {
+                    vlq(&mut mappings, *source as i64 - prev_source as i64);
+                    // line and column in the source, always its start.
+                    mappings.push_str("AA");
+                    prev_source = *source;
+                }
+            }
+        }
+        let sources: Vec<_> = self.sources.iter().map(|s| json_str(s)).collect();
+        format!(
+            "{{\"version\":3,\"file\":{},\"sources\":[{}],\"names\":[],\"mappings\":{}}}",
+            json_str(&self.file),
+            sources.join(","),
+            json_str(&mappings)
+        )
+    }
+}
+
+/// Base64 VLQ, the encoding of the fields of Source Map v3 segments.
+fn vlq(out: &mut String, n: i64) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut n = if n < 0 { (n.unsigned_abs() << 1) | 1 } else { (n as u64) << 1 };
+    loop {
+        let digit = n & 31;
+        n >>= 5;
+        out.push(BASE64[(digit | if n > 0 { 32 } else { 0 }) as usize] as char);
+        if n == 0 {
+            break;
+        }
+    }
+}
+
+fn json_str(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            c if c.is_control() => write!(res, "\\u{:04x}", c as u32).unwrap(),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+/// `src` without its origin markers, and the map of their positions.
+pub(crate) fn source_map(file: &str, src: &str) -> (String, SourceMap) {
+    let mut out = String::with_capacity(src.len());
+    let mut map = SourceMap { file: file.into(), ..Default::default() };
+    let mut lines = vec![Vec::new()];
+    let mut open: Vec<usize> = Vec::new();
+    let mut col = 0;
+    let mut chars = src.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ORIGIN => {
+                let origin: String = chars.by_ref().take_while(|c| *c != ORIGIN_END).collect();
+                let i = map.sources.iter().position(|s| *s == origin).unwrap_or_else(|| {
+                    map.sources.push(origin);
+                    map.sources.len() - 1
+                });
+                open.push(i);
+            }
+            CLOSE => _ = open.pop(),
+            '\n' => {
+                out.push(c);
+                lines.push(Vec::new());
+                col = 0;
+            }
+            c => {
+                let (current, line) = (open.last().copied(), lines.last_mut().unwrap());
+                // a segment only where the origin changes, lines start without one.
+                if line.last().map_or(current.is_some(), |(_, source)| *source != current) {
+                    line.push((col, current));
+                }
+                out.push(c);
+                col += c.len_utf16();
+            }
+        }
+    }
+    if out.ends_with('\n') {
+        lines.pop();
+    }
+    map.lines = lines;
+    (out, map)
+}
+
+/// A hand-written region that can't be carried into the regenerated file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegionError {
+    /// A region of the existing file isn't generated anymore, its content would be lost.
+    Missing { file: String, region: String },
+    /// A `begin` marker without its `end`, or an `end` without a `begin`.
+    Unclosed { file: String, region: String },
+    /// Two regions of one file have the same name.
+    Duplicate { file: String, region: String },
+}
+
+impl std::fmt::Display for RegionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { file, region } => {
+                write!(f, "{file}: hand-written region `{region}` is no longer generated")
+            }
+            Self::Unclosed { file, region } => write!(f, "{file}: region `{region}` isn't closed"),
+            Self::Duplicate { file, region } => write!(f, "{file}: duplicate region `{region}`"),
+        }
+    }
+}
+
+impl std::error::Error for RegionError {}
+
+/// A region kept across regenerations, `body` is its default content. e.g.
+/// ```go
+/// // codeasm:begin imports
+/// // codeasm:end
+/// ```
+pub(crate) fn region(comment: &str, name: impl std::fmt::Display, body: &str) -> String {
+    let body = body.trim_end_matches('\n');
+    let body = if body.is_empty() { String::new() } else { format!("{body}\n") };
+    format!("{comment} codeasm:begin {name}\n{body}{comment} codeasm:end")
+}
+
+/// Regions of `src` by name, with the lines between their markers.
+fn regions<'a>(
+    file: &str,
+    src: &'a str,
+    comment: &str,
+) -> Result<BTreeMap<String, Vec<&'a str>>, RegionError> {
+    let (begin, end) = (format!("{comment} codeasm:begin "), format!("{comment} codeasm:end"));
+    let mut res = BTreeMap::new();
+    let mut open: Option<(String, Vec<&str>)> = None;
+    for line in src.lines() {
+        let stripped = strip_origins(line);
+        let trimmed = stripped.trim();
+        let err = |region: &str| (file.to_string(), region.to_string());
+        if let Some(name) = trimmed.strip_prefix(&begin) {
+            if let Some((region, _)) = open {
+                let (file, region) = err(&region);
+                return Err(RegionError::Unclosed { file, region });
+            }
+            open = Some((name.trim().to_string(), Vec::new()));
+        } else if trimmed == end {
+            let Some((region, lines)) = open.take() else {
+                let (file, region) = err("");
+                return Err(RegionError::Unclosed { file, region });
+            };
+            if res.contains_key(&region) {
+                let (file, region) = err(&region);
+                return Err(RegionError::Duplicate { file, region });
+            }
+            res.insert(region, lines);
+        } else if let Some((_, lines)) = &mut open {
+            lines.push(line);
+        }
+    }
+    match open {
+        Some((region, _)) => Err(RegionError::Unclosed { file: file.into(), region }),
+        None => Ok(res),
+    }
+}
+
+/// Replace the content of the regions of `new` with that of the same regions of `old`.
+pub(crate) fn merge_regions(
+    file: &str,
+    old: &str,
+    new: &str,
+    comment: &str,
+) -> Result<String, RegionError> {
+    let kept = regions(file, old, comment)?;
+    let generated = regions(file, new, comment)?;
+    if let Some(region) = kept.keys().find(|name| !generated.contains_key(*name)) {
+        return Err(RegionError::Missing { file: file.into(), region: region.clone() });
+    }
+    let begin = format!("{comment} codeasm:begin ");
+    let mut res = String::new();
+    let mut skipping = false;
+    for line in new.split_inclusive('\n') {
+        let stripped = strip_origins(line);
+        let trimmed = stripped.trim();
+        if skipping && trimmed == format!("{comment} codeasm:end") {
+            skipping = false;
+        } else if skipping {
+            continue;
+        }
+        res.push_str(line);
+        if let Some(lines) = trimmed.strip_prefix(&begin).and_then(|name| kept.get(name.trim())) {
+            for line in lines {
+                res.push_str(line);
+                res.push('\n');
+            }
+            skipping = true;
+        }
+    }
+    Ok(res)
+}
+
+/// `content` with the hand-written regions of the existing file at `path`.
+fn with_regions(path: &Path, content: String, comment: &str) -> std::io::Result<String> {
+    match std::fs::read_to_string(path) {
+        Ok(old) => merge_regions(&path.to_string_lossy(), &old, &content, comment)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(content),
+        Err(err) => Err(err),
+    }
+}
+
+/// Save one generated file, keeping its hand-written regions. Returns whether it was written.
+pub(crate) fn save_file(path: &Path, content: String, comment: &str) -> std::io::Result<bool> {
+    write_if_changed(path, &with_regions(path, content, comment)?)
+}
+
+/// Save one generated file like [`save_file`], with its source map next to it as `<path>.map`.
+/// `content` still has its origin markers. Returns whether either was written.
+pub(crate) fn save_with_map(path: &Path, content: String, comment: &str) -> std::io::Result<bool> {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let (src, map) = source_map(&name, &with_regions(path, content, comment)?);
+    let mut map_path = path.as_os_str().to_owned();
+    map_path.push(".map");
+    let written = write_if_changed(path, &src)?;
+    Ok(write_if_changed(Path::new(&map_path), &map.to_json())? || written)
+}
+
+/// Whether `path` is relative and stays inside the directory it is joined to.
+pub(crate) fn is_contained(path: &str) -> bool {
+    !path.is_empty() && Path::new(path).components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// FNV-1a, stable across Rust releases unlike `DefaultHasher`.
+fn hash(content: &str) -> u64 {
+    content.bytes().fold(0xcbf29ce484222325, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+fn manifest(hashes: &BTreeMap<String, u64>) -> String {
+    let mut res = String::from("# Generated files and their FNV-1a hashes, kept by codeasm.\n");
+    for (path, hash) in hashes {
+        res.push_str(&format!("{hash:016x}  {path}\n"));
+    }
+    res
+}
+
+fn hashes(files: &BTreeMap<String, String>) -> BTreeMap<String, u64> {
+    files.iter().map(|(path, content)| (path.clone(), hash(content))).collect()
+}
+
+/// Paths listed in the manifest of `dir` and the hashes of their generated content, none if it
+/// has no manifest.
+fn read_manifest(dir: &Path) -> BTreeMap<String, u64> {
+    let Ok(manifest) = std::fs::read_to_string(dir.join(MANIFEST)) else {
+        return BTreeMap::new();
+    };
+    let entries = manifest.lines().filter(|line| !line.starts_with('#'));
+    let entries = entries.filter_map(|line| {
+        let (hash, path) = line.split_once("  ")?;
+        Some((path.to_string(), u64::from_str_radix(hash, 16).ok()?))
+    });
+    entries.filter(|(path, _)| is_contained(path)).collect()
+}
+
+/// Write `content` unless the file already has it, through a temporary file so that readers
+/// never see it half written. Returns whether the file was written.
+pub(crate) fn write_if_changed(path: &Path, content: &str) -> std::io::Result<bool> {
+    if std::fs::read(path).is_ok_and(|old| old == content.as_bytes()) {
+        return Ok(false);
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{name}.tmp"));
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(true)
+}
+
+/// Refuse paths of `files` that would be written outside of the output directory.
+fn check_paths(files: &BTreeMap<String, String>) -> std::io::Result<()> {
+    match files.keys().find(|path| !is_contained(path)) {
+        Some(path) => {
+            let msg = format!("{path} is outside of the output directory");
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Refuse to delete the files of the manifest of `dir` that are no longer in `files` but have
+/// hand-written regions.
+fn check_dropped(
+    dir: &Path,
+    files: &BTreeMap<String, String>,
+    comment: &str,
+) -> std::io::Result<()> {
+    for path in read_manifest(dir).into_keys().filter(|path| !files.contains_key(path)) {
+        let full = dir.join(&path);
+        let old = match std::fs::read_to_string(&full) {
+            Ok(old) => old,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        let file = full.to_string_lossy();
+        let kept = regions(&file, &old, comment)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+        if let Some(region) = kept.into_keys().next() {
+            let err = RegionError::Missing { file: file.into(), region };
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, err));
+        }
+    }
+    Ok(())
+}
+
+/// Write `files`, by path relative to `dir`, replacing `dir` if it exists but keeping the
+/// hand-written regions of its files, marked with `comment`. Files go to a sibling
+/// temporary directory first, which is then renamed to `dir`, so a failed write leaves `dir`
+/// untouched. Dropping a file that has hand-written regions is an error, like dropping one of
+/// its regions.
+///
+/// Files of `dir` that aren't generated are deleted with it, so a non-empty `dir` without a
+/// manifest, i.e. not written by an earlier save, is refused.
+pub(crate) fn write_tree(
+    dir: &Path,
+    mut files: BTreeMap<String, String>,
+    comment: &str,
+) -> std::io::Result<()> {
+    check_paths(&files)?;
+    // `.` or `..` have no file name to put the temporary directory next to.
+    let dir = &if dir.exists() { dir.canonicalize()? } else { dir.to_path_buf() };
+    if dir.file_name().is_none() {
+        let msg = format!("{} has no directory name", dir.display());
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg));
+    }
+    let generated = dir.join(MANIFEST).exists();
+    if dir.exists() && !generated && std::fs::read_dir(dir)?.next().is_some() {
+        let msg =
+            format!("{} has files but no {MANIFEST}, use sync to write into it", dir.display());
+        return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, msg));
+    }
+    for (path, content) in files.iter_mut() {
+        *content = with_regions(&dir.join(path), std::mem::take(content), comment)?;
+    }
+    check_dropped(dir, &files, comment)?;
+    files.insert(MANIFEST.into(), manifest(&hashes(&files)));
+    let name = dir.file_name().unwrap().to_string_lossy();
+    let sibling = |suffix: &str| dir.with_file_name(format!(".{name}.{suffix}"));
+    let (tmp, old) = (sibling("tmp"), sibling("old"));
+    for stale in [&tmp, &old] {
+        if stale.exists() {
+            std::fs::remove_dir_all(stale)?;
+        }
+    }
+    let written = files.into_iter().try_for_each(|(path, content)| {
+        let path = tmp.join(path);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, content)
+    });
+    if let Err(err) = written {
+        _ = std::fs::remove_dir_all(&tmp);
+        return Err(err);
+    }
+    if dir.exists() {
+        std::fs::rename(dir, &old)?;
+    }
+    if let Err(err) = std::fs::rename(&tmp, dir) {
+        if old.exists() {
+            _ = std::fs::rename(&old, dir);
+        }
+        return Err(err);
+    }
+    if old.exists() {
+        std::fs::remove_dir_all(&old)?;
+    }
+    Ok(())
+}
+
+/// Write the files of `files` whose content changed into `dir`, leaving the others untouched.
+/// Hand-written regions, marked with `comment`, are kept.
+/// With `prune`, files of the previous manifest that are no longer generated are deleted, and
+/// so are the directories they leave empty. Those edited since they were generated are kept,
+/// and pruning one with hand-written regions is an error.
+pub(crate) fn sync_tree(
+    dir: &Path,
+    mut files: BTreeMap<String, String>,
+    comment: &str,
+    prune: bool,
+) -> std::io::Result<SaveReport> {
+    check_paths(&files)?;
+    for (path, content) in files.iter_mut() {
+        *content = with_regions(&dir.join(path), std::mem::take(content), comment)?;
+    }
+    if prune {
+        check_dropped(dir, &files, comment)?;
+    }
+    let mut report = SaveReport::default();
+    let previous = read_manifest(dir);
+    let mut listed = hashes(&files);
+    for (path, content) in &files {
+        let full = dir.join(path);
+        let existed = full.exists();
+        std::fs::create_dir_all(full.parent().unwrap())?;
+        match (write_if_changed(&full, content)?, existed) {
+            (false, _) => report.unchanged.push(path.clone()),
+            (true, true) => report.changed.push(path.clone()),
+            (true, false) => report.added.push(path.clone()),
+        }
+    }
+    for (path, generated) in previous.into_iter().filter(|(path, _)| !files.contains_key(path)) {
+        let full = dir.join(&path);
+        let content = match std::fs::read_to_string(&full) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                report.removed.push(path);
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if hash(&content) != generated {
+            // hand-edited, listed until it's deleted by hand.
+            listed.insert(path.clone(), generated);
+            report.edited.push(path);
+            continue;
+        }
+        if !prune {
+            // still generated by an earlier run, a later prune deletes it.
+            listed.insert(path.clone(), generated);
+            report.stale.push(path);
+            continue;
+        }
+        std::fs::remove_file(&full)?;
+        let mut parent = full.parent();
+        while let Some(p) = parent.filter(|p| *p != dir) {
+            if std::fs::remove_dir(p).is_err() {
+                break;
+            }
+            parent = p.parent();
+        }
+        report.removed.push(path);
+    }
+    write_if_changed(&dir.join(MANIFEST), &manifest(&listed))?;
+    Ok(report)
+}
@@ -1,34 +1,179 @@
 use super::*;
 
+/// Kind of a function parameter, in the order Python requires them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ArgKind {
+    /// Positional-only, before the `/` marker.
+    PosOnly,
+    Normal,
+    /// `*args`
+    VarArgs,
+    /// Keyword-only, after `*args` or the bare `*` marker.
+    KwOnly,
+    /// `**kwargs`
+    KwArgs,
+}
+
+/// A function parameter. e.g. `name: str = "x"`
+#[derive(Clone)]
+pub struct Arg {
+    pub name: String,
+    /// Annotation, `Type::unknow()` for none.
+    pub ty: Type,
+    pub default: Option<Expr>,
+    pub kind: ArgKind,
+}
+
+impl Arg {
+    pub fn new(name: impl Display, ty: Type, default: Option<Expr>, kind: ArgKind) -> Self {
+        Self { name: name.to_string(), ty, default, kind }
+    }
+}
+
+impl Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            ArgKind::VarArgs => write!(f, "*")?,
+            ArgKind::KwArgs => write!(f, "**")?,
+            _ => {}
+        }
+        write!(f, "{}", self.name)?;
+        if !self.ty.0.is_empty() {
+            write!(f, ": {}", self.ty)?;
+        }
+        match &self.default {
+            Some(default) if self.ty.0.is_empty() => write!(f, "={default}"),
+            Some(default) => write!(f, " = {default}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Parameters of a function definition.
+///
+/// Ordering rules are checked on every push, so a `DefArgs` always renders to a valid signature.
 #[derive(Clone, Default)]
-pub struct DefArgs(pub String);
+pub struct DefArgs(pub(crate) Vec<Arg>);
 
 impl DefArgs {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn push_arg(self, name: impl Display, default: Option<Expr>) -> Self {
-        let default = default.map_or(String::new(), |d| format!("={d}"));
-        if self.0.is_empty() {
-            Self(format!("{name}{default}"))
-        } else {
-            Self(format!("{name}{default}, "))
+    pub fn args(&self) -> &[Arg] {
+        &self.0
+    }
+
+    pub fn push(mut self, arg: Arg) -> Result<Self, Error> {
+        if self.0.iter().any(|a| a.name == arg.name) {
+            return Err(Error::DuplicateArg(arg.name));
+        }
+        if matches!(arg.kind, ArgKind::VarArgs | ArgKind::KwArgs) && arg.default.is_some() {
+            return Err(Error::StarredDefault(arg.name));
         }
+        if let Some(last) = self.0.last() {
+            if last.kind == ArgKind::KwArgs {
+                return Err(Error::ArgAfterKwArgs(arg.name));
+            }
+            if arg.kind == ArgKind::VarArgs && last.kind >= ArgKind::VarArgs {
+                return Err(match last.kind {
+                    ArgKind::VarArgs => Error::DuplicateVarArgs(arg.name),
+                    _ => Error::MisplacedArg(arg.name),
+                });
+            }
+            if arg.kind < last.kind {
+                return Err(Error::MisplacedArg(arg.name));
+            }
+        }
+        if arg.kind <= ArgKind::Normal
+            && arg.default.is_none()
+            && self.0.iter().any(|a| a.default.is_some())
+        {
+            return Err(Error::NonDefaultAfterDefault(arg.name));
+        }
+        self.0.push(arg);
+        Ok(self)
     }
 
-    pub fn push_vararg(self, name: impl Display) -> Self {
-        if self.0.is_empty() { Self(format!("*{name}")) } else { Self(format!("*{name}, ")) }
+    /// e.g. `a: int = 0`
+    pub fn push_arg(
+        self,
+        name: impl Display,
+        ty: Type,
+        default: Option<Expr>,
+    ) -> Result<Self, Error> {
+        self.push(Arg::new(name, ty, default, ArgKind::Normal))
+    }
+
+    /// Positional-only argument, followed by `/`.
+    pub fn push_posonly(
+        self,
+        name: impl Display,
+        ty: Type,
+        default: Option<Expr>,
+    ) -> Result<Self, Error> {
+        self.push(Arg::new(name, ty, default, ArgKind::PosOnly))
+    }
+
+    /// Keyword-only argument, after `*args` or a bare `*`.
+    pub fn push_kwonly(
+        self,
+        name: impl Display,
+        ty: Type,
+        default: Option<Expr>,
+    ) -> Result<Self, Error> {
+        self.push(Arg::new(name, ty, default, ArgKind::KwOnly))
     }
 
-    pub fn push_kwarg(self, name: impl Display) -> Self {
-        if self.0.is_empty() { Self(format!("**{name}")) } else { Self(format!("**{name}, ")) }
+    /// e.g. `*args: int`
+    pub fn push_vararg(self, name: impl Display, ty: Type) -> Result<Self, Error> {
+        self.push(Arg::new(name, ty, None, ArgKind::VarArgs))
+    }
+
+    /// e.g. `**kwargs: str`
+    pub fn push_kwarg(self, name: impl Display, ty: Type) -> Result<Self, Error> {
+        self.push(Arg::new(name, ty, None, ArgKind::KwArgs))
+    }
+}
+
+impl DefArgs {
+    /// Comma separated parameters, without annotations unless `typed`.
+    pub(crate) fn fmt_params(&self, typed: bool) -> String {
+        let mut parts = Vec::new();
+        let mut starred = false;
+        for (i, arg) in self.0.iter().enumerate() {
+            match arg.kind {
+                ArgKind::VarArgs => starred = true,
+                ArgKind::KwOnly if !starred => {
+                    parts.push("*".to_string());
+                    starred = true;
+                }
+                _ => {}
+            }
+            if typed {
+                parts.push(arg.to_string());
+            } else {
+                parts.push(Arg { ty: Type::unknow(), ..arg.clone() }.to_string());
+            }
+            let next = self.0.get(i + 1).map(|a| a.kind);
+            if arg.kind == ArgKind::PosOnly && next != Some(ArgKind::PosOnly) {
+                parts.push("/".to_string());
+            }
+        }
+        parts.join(", ")
+    }
+}
+
+/// Parameters, bound in the scope of the function body.
+impl Bindings for DefArgs {
+    fn bindings(&self) -> Vec<String> {
+        self.0.iter().map(|a| a.name.clone()).collect()
     }
 }
 
 impl Display for DefArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({})", self.0)
+        write!(f, "({})", self.fmt_params(true))
     }
 }
 
@@ -49,6 +194,33 @@ impl Stmt {
         Self(format!("{name} = {val}"))
     }
 
+    /// Assign to several targets. e.g. `a = b = 0`
+    pub fn assign_many(targets: impl IntoIterator<Item = impl Display>, val: Expr) -> Self {
+        let targets: String = targets.into_iter().map(|t| format!("{t} = ")).collect();
+        Self(format!("{targets}{val}"))
+    }
+
+    /// Unpacking assignment, targets may be starred. e.g. `a, *b = val`
+    pub fn unpack(targets: impl IntoIterator<Item = Expr>, val: Expr) -> Self {
+        let targets: Vec<_> = targets.into_iter().map(|t| t.to_string()).collect();
+        let targets = match targets.as_slice() {
+            [target] => format!("{target},"),
+            _ => targets.join(", "),
+        };
+        Self(format!("{targets} = {val}"))
+    }
+
+    /// Augmented assignment, `op` is the binary operator. e.g. `x += 1`
+    pub fn aug_assign(target: impl Display, op: &str, val: Expr) -> Self {
+        Self(format!("{target} {op}= {val}"))
+    }
+
+    /// Annotated assignment. e.g. `x: int = 0`, or `x: int` without value.
+    pub fn ann_assign(target: impl Display, ty: Type, val: Option<Expr>) -> Self {
+        let val = val.map_or(String::new(), |v| format!(" = {v}"));
+        Self(format!("{target}: {ty}{val}"))
+    }
+
     pub fn global(var: impl Display) -> Self {
         Self(format!("global {var}"))
     }
@@ -59,11 +231,11 @@ impl Stmt {
 
     pub fn func(name: impl Display, args: DefArgs, ret: Type, body: Block) -> Self {
         let ret = if ret.0.is_empty() { String::new() } else { format!(" -> {ret}") };
-        Self(format!("def {name}{args}{ret}:\n{body}"))
+        Self(format!("def {name}{args}{ret}:\n{body:#}"))
     }
 
     pub fn async_func(name: impl Display, args: DefArgs, ret: Type, body: Block) -> Self {
-        Self(format!("async {}", Self::func(name, args, ret, body)))
+        Self(format!("async {:#}", Self::func(name, args, ret, body)))
     }
 
     pub fn class(
@@ -73,15 +245,16 @@ impl Stmt {
     ) -> Self {
         let inherit = inherit.into_iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ");
         if inherit.is_empty() {
-            Self(format!("class {name}:\n{body}"))
+            Self(format!("class {name}:\n{body:#}"))
         } else {
-            Self(format!("class {name}({inherit}):\n{body}"))
+            Self(format!("class {name}({inherit}):\n{body:#}"))
         }
     }
 }
 
+/// The code of a module and its header, see [`File::header`].
 #[derive(Clone, Default)]
-pub struct File(pub String);
+pub struct File(pub String, pub Option<crate::output::Header>);
 
 impl File {
     pub fn new() -> Self {
@@ -89,23 +262,47 @@ impl File {
     }
 
     pub fn push(self, stmt: Stmt) -> Self {
-        Self(format!("{self}{stmt}\n"))
+        Self(format!("{}{stmt:#}\n", self.0), self.1)
     }
 
     /// push stmt to `&mut File`
     pub fn pushs(&mut self, stmt: Stmt) -> &mut Self {
-        writeln!(self.0, "{stmt}").unwrap();
+        writeln!(self.0, "{stmt:#}").unwrap();
         self
     }
 
-    /// Save to file.
+    /// Put `header` at the top of the file, instead of the one of its [`Package`]. e.g.
+    /// `# Code generated by sdkgen. DO NOT EDIT.`
+    pub fn header(self, header: &crate::output::Header) -> Self {
+        Self(self.0, Some(header.clone()))
+    }
+
+    /// Save to file, left untouched if it already has the same content. The hand-written regions
+    /// of an existing file are kept, see [`Stmt::region`].
     pub fn save(self, path: &str) -> std::io::Result<()> {
-        std::fs::write(path, self.to_string())
+        crate::output::save_file(path.as_ref(), self.to_string(), "#").map(|_| ())
+    }
+
+    /// The rendered file and the map of its lines to the origins of their statements, see
+    /// [`Stmt::origin`]. `file` is the name of the generated file in the map.
+    pub fn source_map(&self, file: &str) -> (String, crate::output::SourceMap) {
+        crate::output::source_map(file, &self.source())
+    }
+
+    /// [`File::save`], with the source map next to the file as `<path>.map`.
+    pub fn save_with_source_map(self, path: &str) -> std::io::Result<()> {
+        crate::output::save_with_map(path.as_ref(), self.source(), "#").map(|_| ())
+    }
+
+    /// The rendered file, with the origin markers of its statements.
+    fn source(&self) -> String {
+        let header = self.1.as_ref().map(|h| h.render("#")).unwrap_or_default();
+        header + &self.0
     }
 }
 
 impl Display for File {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", crate::output::strip_origins(&self.source()))
     }
 }
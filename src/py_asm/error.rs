@@ -0,0 +1,58 @@
+use super::*;
+
+/// Errors reported while building Python code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The same parameter name was used twice in one signature.
+    DuplicateArg(String),
+    /// A parameter without default follows one with a default.
+    NonDefaultAfterDefault(String),
+    /// A parameter was pushed after one of a later kind. e.g. positional-only after `*args`.
+    MisplacedArg(String),
+    /// More than one `*args` parameter.
+    DuplicateVarArgs(String),
+    /// Any parameter after `**kwargs`.
+    ArgAfterKwArgs(String),
+    /// A default on `*args` or `**kwargs`.
+    StarredDefault(String),
+    /// A `parametrize` case doesn't have one value per name.
+    ParametrizeCase(String),
+    /// A package name that isn't an identifier.
+    InvalidPackage(String),
+    /// A module path of a package that isn't dotted identifiers, or names an `__init__` module.
+    InvalidModule(String),
+    /// A module path that is also the package of another module. e.g. `api` with `api.client`
+    ModuleIsPackage(String),
+    /// An export of a module the package doesn't have.
+    UnknownModule(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateArg(name) => write!(f, "duplicate argument `{name}`"),
+            Self::NonDefaultAfterDefault(name) => {
+                write!(f, "non-default argument `{name}` follows default argument")
+            }
+            Self::MisplacedArg(name) => write!(f, "argument `{name}` is out of order"),
+            Self::DuplicateVarArgs(name) => {
+                write!(f, "`*{name}` is not allowed, `*args` is already defined")
+            }
+            Self::ArgAfterKwArgs(name) => write!(f, "argument `{name}` follows `**kwargs`"),
+            Self::StarredDefault(name) => {
+                write!(f, "starred argument `{name}` can't have a default")
+            }
+            Self::ParametrizeCase(case) => {
+                write!(f, "parametrize case `{case}` doesn't have one value per name")
+            }
+            Self::InvalidPackage(name) => write!(f, "invalid package name `{name}`"),
+            Self::InvalidModule(path) => write!(f, "invalid module path `{path}`"),
+            Self::ModuleIsPackage(path) => {
+                write!(f, "module `{path}` is also the package of another module")
+            }
+            Self::UnknownModule(path) => write!(f, "unknown module `{path}`"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
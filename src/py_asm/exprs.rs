@@ -38,9 +38,29 @@ impl Expr {
         Self(format!("{{{elts}}}"))
     }
 
-    pub fn lambda(args: impl IntoIterator<Item = impl ToString>, body: Block) -> Self {
-        let args = args.into_iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
-        Self(format!("lambda {args}:\n{body}"))
+    /// e.g. `(lambda x, y=1: x + y)`. A lambda can't have annotations, those of `args` are
+    /// dropped.
+    pub fn lambda(args: DefArgs, body: Expr) -> Self {
+        let args = args.fmt_params(false);
+        if args.is_empty() {
+            Self(format!("(lambda: {body})"))
+        } else {
+            Self(format!("(lambda {args}: {body})"))
+        }
+    }
+
+    /// A function value whose body needs statements, which a lambda can't hold.
+    ///
+    /// Returns a nested `def` to push before the use site, named `_lambda`, `_lambda_1`, ...
+    /// whichever isn't bound in `scope`, where it is bound, and the expression referring to it.
+    pub fn hoisted_lambda(
+        args: DefArgs,
+        ret: Type,
+        body: Block,
+        scope: &mut crate::naming::Scope,
+    ) -> (Stmt, Self) {
+        let name = scope.fresh_name("_lambda");
+        (Stmt::func(&name, args, ret, body), Self(name))
     }
 
     pub fn listcomp(
@@ -96,10 +116,43 @@ impl Expr {
         Self(format!("({self} {op} {rhs})"))
     }
 
+    /// Chained comparison. e.g. `(a < b <= c)`
+    pub fn compare(self, ops: impl IntoIterator<Item = (&'static str, Expr)>) -> Self {
+        let ops: String = ops.into_iter().map(|(op, rhs)| format!(" {op} {rhs}")).collect();
+        Self(format!("({self}{ops})"))
+    }
+
+    /// Conditional expression. e.g. `(a if cond else b)`
+    pub fn if_else(self, cond: Expr, else_: Expr) -> Self {
+        Self(format!("({self} if {cond} else {else_})"))
+    }
+
+    /// Assignment expression. e.g. `(n := len(a))`
+    pub fn walrus(name: impl Display, val: Expr) -> Self {
+        Self(format!("({name} := {val})"))
+    }
+
+    /// e.g. `*args`
+    pub fn star(self) -> Self {
+        Self(format!("*{self}"))
+    }
+
+    /// e.g. `**kwargs`
+    pub fn double_star(self) -> Self {
+        Self(format!("**{self}"))
+    }
+
     pub fn index(self, index: Expr) -> Self {
         Self(format!("{self}[{index}]"))
     }
 
+    /// e.g. `a[1:2:3]`, `a[:n]`
+    pub fn slice(self, lower: Option<Expr>, upper: Option<Expr>, step: Option<Expr>) -> Self {
+        let [lower, upper] = [lower, upper].map(|e| e.map_or(String::new(), |e| e.to_string()));
+        let step = step.map_or(String::new(), |s| format!(":{s}"));
+        Self(format!("{self}[{lower}:{upper}{step}]"))
+    }
+
     pub fn await_(expr: Expr) -> Self {
         Self(format!("await {expr}"))
     }
@@ -109,10 +162,103 @@ impl Expr {
         args: impl IntoIterator<Item = Self>,
         kwargs: impl IntoIterator<Item = (impl Display, Self)>,
     ) -> Self {
-        let args = args.into_iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
-        let kwargs: String =
-            kwargs.into_iter().map(|(kw, v)| format!(", {kw}={v}")).collect();
-        Self(format!("{self}({args}{kwargs})"))
+        let args = args.into_iter().map(|a| a.to_string());
+        let kwargs = kwargs.into_iter().map(|(kw, v)| format!("{kw}={v}"));
+        Self(format!("{self}({})", args.chain(kwargs).collect::<Vec<_>>().join(", ")))
+    }
+}
+
+/// Target Python version, for syntax that depends on it. e.g. `Version(3, 12)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u8, pub u8);
+
+#[derive(Clone)]
+enum FPart {
+    Lit(String),
+    /// Expression, conversion (`r`, `s` or `a`) and format spec.
+    Expr(Expr, Option<char>, String),
+}
+
+/// An f-string built from literal text and interpolated expressions.
+///
+/// Braces in literal text are doubled, and the quotes are chosen so that string literals nested
+/// in the expressions don't end the f-string early, which isn't allowed before Python 3.12. When
+/// no quote works, or for Python older than 3.6, it falls back to `"...".format(...)`. `.format`
+/// takes the same conversions and format specs and exists in every Python 3, so a `%` fallback
+/// isn't needed.
+#[derive(Clone, Default)]
+pub struct FString(Vec<FPart>);
+
+impl FString {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Literal text.
+    pub fn lit(mut self, text: impl ToString) -> Self {
+        self.0.push(FPart::Lit(text.to_string()));
+        self
+    }
+
+    /// e.g. `{x}`
+    pub fn expr(self, expr: Expr) -> Self {
+        self.expr_fmt(expr, None, "")
+    }
+
+    /// e.g. `expr_fmt(x, Some('r'), ">10")` -> `{x!r:>10}`
+    pub fn expr_fmt(mut self, expr: Expr, conv: Option<char>, spec: impl ToString) -> Self {
+        self.0.push(FPart::Expr(expr, conv, spec.to_string()));
+        self
+    }
+
+    pub fn build(self, version: Version) -> Expr {
+        let exprs: Vec<String> = self
+            .0
+            .iter()
+            .filter_map(|p| match p {
+                FPart::Expr(e, ..) if e.0.starts_with("lambda") => Some(format!("({e})")),
+                FPart::Expr(e, ..) if e.0.starts_with('{') => Some(format!(" {e} ")),
+                FPart::Expr(e, ..) => Some(e.to_string()),
+                FPart::Lit(_) => None,
+            })
+            .collect();
+        let quote = if version >= Version(3, 12) {
+            Some("\"")
+        } else if version < Version(3, 6) || exprs.iter().any(|e| e.contains('\\')) {
+            None
+        } else {
+            ["\"", "'", "\"\"\"", "\'\'\'"]
+                .into_iter()
+                .find(|q| exprs.iter().all(|e| !e.contains(q)))
+        };
+        let q = quote.unwrap_or("\"");
+        let mut body = String::new();
+        let mut exprs = exprs.into_iter();
+        for part in &self.0 {
+            match part {
+                FPart::Lit(text) => {
+                    let text = text.replace('\\', "\\\\").replace('\n', "\\n");
+                    let text = text.replace('\r', "\\r").replace('\t', "\\t");
+                    let text = text.replace(&q[..1], &format!("\\{}", &q[..1]));
+                    body.push_str(&text.replace('{', "{{").replace('}', "}}"));
+                }
+                FPart::Expr(_, conv, spec) => {
+                    let expr = exprs.next().unwrap();
+                    let expr = if quote.is_some() { expr } else { String::new() };
+                    let conv = conv.map_or(String::new(), |c| format!("!{c}"));
+                    let spec = if spec.is_empty() { String::new() } else { format!(":{spec}") };
+                    write!(body, "{{{expr}{conv}{spec}}}").unwrap();
+                }
+            }
+        }
+        if quote.is_some() {
+            return Expr(format!("f{q}{body}{q}"));
+        }
+        let args = self.0.into_iter().filter_map(|p| match p {
+            FPart::Expr(e, ..) => Some(e),
+            FPart::Lit(_) => None,
+        });
+        Expr(format!("\"{body}\"")).attr("format").call(args, Vec::<(&str, _)>::new())
     }
 }
 
@@ -128,19 +274,19 @@ impl Not for Expr {
         Self(format!("not {self}"))
     }
 }
-impl Into<Expr> for String {
-    fn into(self) -> Expr {
-        Expr::str("", self)
+impl From<String> for Expr {
+    fn from(value: String) -> Self {
+        Expr::str("", value)
     }
 }
-impl Into<Expr> for &str {
-    fn into(self) -> Expr {
-        Expr::str("", self)
+impl From<&str> for Expr {
+    fn from(value: &str) -> Self {
+        Expr::str("", value)
     }
 }
-impl Into<Expr> for bool {
-    fn into(self) -> Expr {
-        if self { Expr::raw("True") } else { Expr::raw("False") }
+impl From<bool> for Expr {
+    fn from(value: bool) -> Self {
+        if value { Expr::raw("True") } else { Expr::raw("False") }
     }
 }
 impl Display for Expr {
@@ -156,7 +302,7 @@ macro_rules! impl_binop { ($($t:ident, $f:ident, $op:expr),*) => {$(
     }
 )*}}
 macro_rules! impl_into { ($($t:ty),*) => {$(
-    impl Into<Expr> for $t { fn into(self) -> Expr { Expr::raw(self) } }
+    impl From<$t> for Expr { fn from(value: $t) -> Self { Expr::raw(value) } }
 )*}}
 impl_binop!(Add, add, "+", Sub, sub, "-", Mul, mul, "*", Div, div, "/");
 impl_binop!(Rem, rem, "%", BitAnd, bitand, "&", BitOr, bitor, "|");
@@ -0,0 +1,171 @@
+//! Tokenizer for rendered Python source, used by the checks that run over a [`File`] and to
+//! indent nested blocks.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Name,
+    Keyword,
+    Number,
+    /// String, bytes or f-string literal, prefix included.
+    Str,
+    /// A string literal missing its closing quote.
+    Unterminated,
+    Op,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Token {
+    pub kind: Kind,
+    pub text: String,
+    /// 1-based line.
+    pub line: usize,
+}
+
+impl Token {
+    pub fn is(&self, text: &str) -> bool {
+        matches!(self.kind, Kind::Keyword | Kind::Op) && self.text == text
+    }
+}
+
+/// A logical line, spanning several physical lines inside brackets or after `\`.
+#[derive(Debug, Clone)]
+pub(crate) struct Line {
+    pub indent: usize,
+    /// 1-based first and last physical line.
+    pub line: usize,
+    pub end_line: usize,
+    pub toks: Vec<Token>,
+}
+
+pub(crate) const KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
+const OPS: [&str; 24] = [
+    "**=", "//=", ">>=", "<<=", "...", "->", ":=", "**", "//", "<<", ">>", "<=", ">=", "==", "!=",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "@=",
+];
+
+const STR_PREFIXES: [&str; 9] = ["r", "b", "f", "u", "rb", "br", "fr", "rf", ""];
+
+pub(crate) fn logical_lines(src: &str) -> Vec<Line> {
+    let bytes = src.as_bytes();
+    let mut lines = Vec::new();
+    let mut toks: Vec<Token> = Vec::new();
+    let (mut i, mut line, mut depth) = (0, 1, 0usize);
+    let (mut indent, mut start_line, mut line_start) = (0, 1, true);
+    while i < src.len() {
+        if line_start {
+            let ws = src[i..].find(|c| c != ' ' && c != '\t').unwrap_or(src.len() - i);
+            indent = src[i..i + ws].chars().map(|c| if c == '\t' { 8 } else { 1 }).sum();
+            (i, start_line, line_start) = (i + ws, line, false);
+            continue;
+        }
+        let c = src[i..].chars().next().unwrap();
+        let start = i;
+        match c {
+            '\n' => {
+                i += 1;
+                if depth == 0 {
+                    if !toks.is_empty() {
+                        let toks = std::mem::take(&mut toks);
+                        lines.push(Line { indent, line: start_line, end_line: line, toks });
+                    }
+                    line_start = true;
+                }
+                line += 1;
+            }
+            '\\' if bytes.get(i + 1) == Some(&b'\n') => {
+                i += 2;
+                line += 1;
+            }
+            '#' => i = src[i..].find('\n').map_or(src.len(), |n| i + n),
+            c if c.is_whitespace() => i += c.len_utf8(),
+            c if c.is_alphabetic() || c == '_' => {
+                let len = src[i..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .unwrap_or(src.len() - i);
+                i += len;
+                let word = &src[start..i];
+                let prefix = STR_PREFIXES.contains(&word.to_lowercase().as_str());
+                if prefix && matches!(bytes.get(i), Some(b'"' | b'\'')) {
+                    let kind;
+                    (i, kind) = string_end(src, i);
+                    toks.push(Token { kind, text: src[start..i].into(), line });
+                    line += src[start..i].matches('\n').count();
+                } else {
+                    let kind = if KEYWORDS.contains(&word) { Kind::Keyword } else { Kind::Name };
+                    toks.push(Token { kind, text: word.into(), line });
+                }
+            }
+            '"' | '\'' => {
+                let kind;
+                (i, kind) = string_end(src, i);
+                toks.push(Token { kind, text: src[start..i].into(), line });
+                line += src[start..i].matches('\n').count();
+            }
+            c if c.is_ascii_digit()
+                || (c == '.' && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit())) =>
+            {
+                i += 1;
+                while let Some(&b) = bytes.get(i) {
+                    let sign = matches!(bytes[i - 1], b'e' | b'E') && (b == b'+' || b == b'-');
+                    if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || sign {
+                        i += 1;
+                    } else {
+                        break;
+                    }
+                }
+                toks.push(Token { kind: Kind::Number, text: src[start..i].into(), line });
+            }
+            _ => {
+                let len = OPS
+                    .iter()
+                    .find(|op| src[i..].starts_with(**op))
+                    .map_or(c.len_utf8(), |op| op.len());
+                i += len;
+                match c {
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                toks.push(Token { kind: Kind::Op, text: src[start..i].into(), line });
+            }
+        }
+    }
+    if !toks.is_empty() {
+        lines.push(Line { indent, line: start_line, end_line: line, toks });
+    }
+    lines
+}
+
+/// 1-based lines that start inside a multi-line string, their leading whitespace is part of the
+/// string.
+pub(crate) fn string_lines(src: &str) -> Vec<usize> {
+    let toks = logical_lines(src).into_iter().flat_map(|l| l.toks);
+    let strs = toks.filter(|t| matches!(t.kind, Kind::Str | Kind::Unterminated));
+    strs.flat_map(|t| t.line + 1..=t.line + t.text.matches('\n').count()).collect()
+}
+
+/// Index after the string literal whose opening quote is at `i`, and whether it is closed.
+fn string_end(src: &str, i: usize) -> (usize, Kind) {
+    let bytes = src.as_bytes();
+    let quote = bytes[i];
+    let triple = bytes.get(i + 1) == Some(&quote) && bytes.get(i + 2) == Some(&quote);
+    let mut j = if triple { i + 3 } else { i + 1 };
+    while j < bytes.len() {
+        match bytes[j] {
+            b'\\' => j += 2,
+            b if b == quote && !triple => return (j + 1, Kind::Str),
+            b if b == quote && bytes.get(j + 1) == Some(&b) && bytes.get(j + 2) == Some(&b) => {
+                return (j + 3, Kind::Str);
+            }
+            b'\n' if !triple => return (j, Kind::Unterminated),
+            _ => j += 1,
+        }
+    }
+    (bytes.len(), Kind::Unterminated)
+}
@@ -2,12 +2,23 @@ pub mod exprs;
 pub mod stmts;
 pub mod types;
 pub mod decls;
+pub mod error;
+mod lexer;
+pub mod naming;
+pub mod package;
+pub mod testing;
+pub mod validate;
 
-pub use decls::{DefArgs, File};
-pub use exprs::Expr;
+pub use decls::{Arg, ArgKind, DefArgs, File};
+pub use error::Error;
+pub use exprs::{Expr, FString, Version};
+pub use package::Package;
+pub(super) use crate::naming::Bindings;
 pub(super) use std::fmt::{Display, Write};
-pub use stmts::{Block, Stmt};
+pub use stmts::{Block, Pattern, Stmt};
+pub use testing::TestCase;
 pub use types::Type;
+pub use validate::{Diagnostic, DiagnosticKind};
 
 mod tests {
     #[test]
@@ -23,4 +34,385 @@ mod tests {
             )], Block::new()));
         print!("{file}")
     }
+
+    #[test]
+    fn def_args() {
+        use super::*;
+        let args = DefArgs::new()
+            .push_posonly("a", Type::int(), None)
+            .and_then(|args| args.push_arg("b", Type::unknow(), Some(1.into())))
+            .and_then(|args| args.push_kwonly("c", Type::str(), None))
+            .and_then(|args| args.push_kwarg("kw", Type::unknow()))
+            .unwrap();
+        assert_eq!(args.to_string(), "(a: int, /, b=1, *, c: str, **kw)");
+
+        let args = DefArgs::new().push_arg("a", Type::int(), Some(0.into())).unwrap();
+        assert!(args.clone().push_arg("b", Type::unknow(), None).is_err());
+        assert!(args.clone().push_posonly("b", Type::unknow(), Some(0.into())).is_err());
+        let args = args.push_vararg("args", Type::unknow()).unwrap();
+        assert!(args.clone().push_vararg("more", Type::unknow()).is_err());
+        assert!(args.push_kwarg("a", Type::unknow()).is_err());
+        let kwargs = Arg::new("kw", Type::unknow(), Some(1.into()), ArgKind::KwArgs);
+        assert_eq!(DefArgs::new().push(kwargs).err(), Some(Error::StarredDefault("kw".into())));
+    }
+
+    #[test]
+    fn match_() {
+        use super::*;
+        let x = Expr::raw("x");
+        let point = Pattern::class(
+            Expr::raw("Point"),
+            [Pattern::capture("x")],
+            [("y", Pattern::value(0.into()))],
+        );
+        let seq = Pattern::seq([Pattern::capture("first"), Pattern::star("rest")]);
+        let print = Expr::raw("print").call([x.clone()], Vec::<(&str, _)>::new());
+        let match_ = Stmt::match_(
+            Expr::raw("p"),
+            [
+                (point, Some(x.clone().binop(">", 0.into())), Block::new().push(print.into())),
+                (seq, None, Block::new().push(Stmt::ret(None))),
+                (Pattern::wildcard(), None, Block::new().push(Stmt::pass())),
+            ],
+        );
+        let func = Stmt::func("f", DefArgs::new(), Type::unknow(), Block::new().push(match_));
+        let expected = [
+            "def f():",
+            "    match p:",
+            "        case Point(x, y=0) if (x > 0):",
+            "            print(x)",
+            "        case [first, *rest]:",
+            "            return",
+            "        case _:",
+            "            pass",
+        ];
+        assert_eq!(func.to_string(), expected.map(|l| format!("{l}\n")).concat());
+        let unpack = Stmt::unpack([x.clone(), Expr::raw("y").star()], Expr::raw("a"));
+        assert_eq!(unpack.to_string(), "x, *y = a");
+        assert_eq!(x.slice(None, Some(2.into()), Some((-1).into())).to_string(), "x[:2:-1]");
+        assert_eq!(Stmt::aug_assign("x", "//", 2.into()).to_string(), "x //= 2");
+        assert_eq!(Stmt::ann_assign("x", Type::int(), Some(0.into())).to_string(), "x: int = 0");
+    }
+
+    #[test]
+    fn fstring() {
+        use super::*;
+        let name = Expr::raw("user").index("name".into());
+        let fstr = FString::new().lit("{id} ").expr_fmt(name, Some('r'), ">10").lit(" \"ok\"");
+        let py312 = r#"f"{{id}} {user["name"]!r:>10} \"ok\"""#;
+        assert_eq!(fstr.clone().build(Version(3, 12)).to_string(), py312);
+        let py38 = r#"f'{{id}} {user["name"]!r:>10} "ok"'"#;
+        assert_eq!(fstr.clone().build(Version(3, 8)).to_string(), py38);
+        assert_eq!(
+            fstr.build(Version(3, 5)).to_string(),
+            r#""{{id}} {!r:>10} \"ok\"".format(user["name"])"#
+        );
+        let both = FString::new().expr(Expr::raw(r#"d["a"] + 'b'"#)).lit("\t\r\n");
+        let py38 = r#"f"""{d["a"] + 'b'}\t\r\n""""#;
+        assert_eq!(both.build(Version(3, 8)).to_string(), py38);
+    }
+
+    #[test]
+    fn lambda() {
+        use super::*;
+        let args = DefArgs::new()
+            .push_arg("x", Type::int(), None)
+            .and_then(|args| args.push_arg("y", Type::int(), Some(1.into())))
+            .unwrap();
+        let lambda = Expr::lambda(args.clone(), Expr::raw("x") + Expr::raw("y"));
+        assert_eq!(lambda.to_string(), "(lambda x, y=1: (x + y))");
+        assert_eq!(Expr::lambda(DefArgs::new(), 0.into()).to_string(), "(lambda: 0)");
+
+        let mut scope = crate::naming::Scope::new();
+        scope.bind("_lambda");
+        let body = Block::new().push(Stmt::raw("print(x)")).push(Stmt::ret(Some(Expr::raw("y"))));
+        let (def, f) = Expr::hoisted_lambda(args, Type::int(), body, &mut scope);
+        assert_eq!(f.to_string(), "_lambda_1");
+        assert!(def.to_string().starts_with("def _lambda_1(x: int, y: int = 1) -> int:\n"));
+        assert!(scope.is_bound("_lambda_1"));
+    }
+
+    #[test]
+    fn validate() {
+        use super::*;
+        let body = Block::new().push(Stmt::for_(
+            [("x", Expr::raw("xs"))],
+            Block::new().push(Stmt::yield_(Some(Expr::raw("x")))),
+            Block::new(),
+        ));
+        let file = File::new().push(Stmt::func("gen", DefArgs::new(), Type::unknow(), body));
+        assert!(file.validate().is_ok());
+
+        let file = file
+            .push(Stmt::func("todo", DefArgs::new(), Type::unknow(), Block::new()))
+            .push(Stmt::brk())
+            .push(Stmt::raw("def f(a=1, b): pass"))
+            .push(Stmt::func(
+                "g",
+                DefArgs::new(),
+                Type::unknow(),
+                Block::new().push(Stmt::nonlocal("n")),
+            ));
+        let diags = file.validate().unwrap_err();
+        let kinds: Vec<_> = diags.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                DiagnosticKind::EmptyBody,
+                DiagnosticKind::Misplaced,
+                DiagnosticKind::Args,
+                DiagnosticKind::NonlocalBinding,
+            ]
+        );
+        assert_eq!(diags[3].to_string(), "10: def g: no binding for nonlocal `n` found");
+
+        let todo = Stmt::func("todo", DefArgs::new(), Type::unknow(), Block::new());
+        let file = File::new().push(todo).fill_empty_bodies();
+        assert_eq!(file.to_string(), "def todo():\n    pass\n\n");
+        assert!(file.validate().is_ok());
+    }
+
+    #[test]
+    fn naming() {
+        use super::naming::*;
+        assert_eq!(ident("userId"), "user_id");
+        assert_eq!(ident("user-id"), "user_id");
+        assert_eq!(ident("class"), "class_");
+        assert_eq!(ident("type"), "type_");
+        assert_eq!(ident("2fa_enabled"), "_2fa_enabled");
+        assert_eq!(class_name("http_server"), "HttpServer");
+        assert_eq!(constant("maxRetries"), "MAX_RETRIES");
+        assert_eq!(class_name("none"), "None_");
+    }
+
+    #[test]
+    fn scope() {
+        use super::*;
+        use crate::naming::{Bindings, Scope};
+        let args = DefArgs::new().push_arg("tmp", Type::unknow(), None).unwrap();
+        let mut scope = Scope::new();
+        scope.enter_with(&args);
+        let loop_ = Stmt::for_(
+            [("i, item", Expr::raw("enumerate(xs)"))],
+            Block::new().push(Stmt::assign("total", Expr::raw("item"))),
+            Block::new(),
+        );
+        assert_eq!(loop_.bindings(), ["i", "item", "total"]);
+        scope.track(loop_);
+        scope.track(Stmt::import("os.path"));
+        assert!(scope.is_bound("os") && scope.is_bound("total"));
+        assert_eq!(scope.fresh_name("tmp"), "tmp_1");
+
+        let body = Block::new().push(Stmt::assign("local", 1.into()));
+        let inner = Stmt::func("f", DefArgs::new(), Type::unknow(), body);
+        assert_eq!(inner.bindings(), ["f"]);
+        scope.enter().bind("item");
+        assert_eq!(scope.shadowing()[0].name, "item");
+    }
+
+    #[test]
+    fn testing() {
+        use super::*;
+        let doc = Stmt::docstring("Add two numbers.", [("add(1, 2)", "3")]);
+        assert_eq!(doc.0, "\"\"\"Add two numbers.\n\n>>> add(1, 2)\n3\n\"\"\"");
+        let quoted = Stmt::docstring("Say \"hi\"", Vec::<(&str, &str)>::new());
+        assert_eq!(quoted.0, r#""""Say "hi\"""""#);
+        assert_eq!(File::new().push(quoted).validate(), Ok(()));
+        let broken = File::new().push(Stmt::raw(r#""""Say "hi"""""#)).validate().unwrap_err();
+        assert_eq!(broken[0].kind, DiagnosticKind::Syntax);
+        let cases = [[Expr::raw("1"), Expr::raw("2")], [Expr::raw("3"), Expr::raw("4")]];
+        let param = Stmt::parametrize(["a", "b"], cases).unwrap();
+        assert_eq!(param.0, "@pytest.mark.parametrize(\"a, b\", [(1, 2), (3, 4)])");
+        assert!(Stmt::parametrize(["a", "b"], [[Expr::raw("1")]]).is_err());
+
+        let args = DefArgs::new().push_arg("a", Type::unknow(), None);
+        let args = args.and_then(|args| args.push_arg("b", Type::unknow(), None)).unwrap();
+        let check = Stmt::assert(Expr::raw("a < b"), None);
+        let fixture = Block::new().push(Stmt::yield_(Some(Expr::raw("Client()"))));
+        let case = TestCase::new("TestClient")
+            .set_up(Block::new().push(Stmt::assign("self.client", Expr::raw("Client()"))))
+            .test("close", Block::new().push(Stmt::raw("self.client.close()")));
+        let file = File::new()
+            .push(Stmt::import("pytest"))
+            .push(Stmt::fixture("client", "module", DefArgs::new(), fixture))
+            .push(param)
+            .push(Stmt::test("order", args, Block::new().push(check)))
+            .push(case.into());
+        assert_eq!(file.validate(), Ok(()));
+        let src = file.to_string();
+        assert!(src.contains("@pytest.fixture(scope=\"module\")\ndef client():\n"));
+        assert!(src.contains(")])\ndef test_order(a, b):\n    assert a < b\n"));
+        assert!(src.contains("class TestClient(unittest.TestCase):\n    def setUp(self):\n"));
+        assert!(src.contains("\n    def test_close(self):\n        self.client.close()\n"));
+    }
+
+    #[test]
+    fn package() {
+        use super::*;
+        for name in ["../escaped", "a\"b", "class", "__init__", "acme.sdk"] {
+            let err = Package::new(name, "0.1.0", Version(3, 9)).err();
+            assert_eq!(err, Some(Error::InvalidPackage(name.into())));
+        }
+        let pkg = Package::new("acme_sdk", "0.1.0", Version(3, 9))
+            .unwrap()
+            .dependency("httpx>=0.27")
+            .dependency("attrs; python_version < \"3.10\"");
+        let user = Stmt::class("User", [""; 0], Block::new().push(Stmt::pass()));
+        let models = File::new().push(user);
+        let client = File::new().push(pkg.import("api.client", "models", ["User"]));
+        let pkg = pkg
+            .module("models", models)
+            .and_then(|pkg| pkg.module("api.client", client))
+            .and_then(|pkg| pkg.export("models", ["User"]))
+            .unwrap();
+        assert_eq!(
+            pkg.clone().module("api.class", File::new()).err(),
+            Some(Error::InvalidModule("api.class".into()))
+        );
+        assert_eq!(
+            pkg.clone().module("api", File::new()).err(),
+            Some(Error::ModuleIsPackage("api".into()))
+        );
+        assert_eq!(
+            pkg.clone().module("models.user", File::new()).err(),
+            Some(Error::ModuleIsPackage("models".into()))
+        );
+        assert_eq!(
+            pkg.clone().export("api.errors", ["E"]).err(),
+            Some(Error::UnknownModule("api.errors".into()))
+        );
+        let relative = pkg.clone().relative();
+        let import = relative.import("api.client", "models", ["User"]);
+        assert_eq!(import.0, "from ..models import User");
+        assert_eq!(relative.import("api.client", "api.errors", ["E"]).0, "from .errors import E");
+        assert_eq!(relative.import("api.client", "api", ["a"]).0, "from . import a");
+
+        let files = pkg.render();
+        let paths: Vec<_> = files.keys().map(String::as_str).collect();
+        assert_eq!(
+            paths,
+            [
+                "acme_sdk/__init__.py",
+                "acme_sdk/api/__init__.py",
+                "acme_sdk/api/client.py",
+                "acme_sdk/models.py",
+                "acme_sdk/py.typed",
+                "pyproject.toml",
+            ]
+        );
+        assert_eq!(
+            files["acme_sdk/__init__.py"],
+            "from acme_sdk.models import User\n__all__ = [\"User\"]\n"
+        );
+        assert_eq!(files["acme_sdk/api/client.py"], "from acme_sdk.models import User\n");
+        let pyproject = &files["pyproject.toml"];
+        assert!(pyproject.contains("name = \"acme-sdk\"\nversion = \"0.1.0\"\n"));
+        assert!(pyproject.contains("requires-python = \">=3.9\"\ndependencies = [\n"));
+        assert!(pyproject.contains("    \"attrs; python_version < \\\"3.10\\\"\",\n"));
+
+        use crate::output::Header;
+        let own = File::new().push(Stmt::pass()).header(&Header::new("other"));
+        let files = pkg.clone().module("own", own).unwrap().header(Header::new("sdkgen")).render();
+        assert_eq!(files["acme_sdk/own.py"], "# Code generated by other. DO NOT EDIT.\n\npass\n");
+        let generated = "# Code generated by sdkgen. DO NOT EDIT.\n\n";
+        assert!(files["acme_sdk/models.py"].starts_with(generated));
+        assert!(files["acme_sdk/api/__init__.py"].starts_with("# Code generated by sdkgen."));
+
+        let dir = std::env::temp_dir().join(format!("codeasm-package-{}", std::process::id()));
+        pkg.save(&dir).unwrap();
+        assert!(dir.join("acme_sdk/py.typed").exists());
+        let outside = [("../escaped/m.py".to_string(), String::new())].into();
+        let err = crate::output::write_tree(&dir, outside, "#").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn regions() {
+        use super::*;
+        use crate::output::RegionError;
+        let path = std::env::temp_dir().join(format!("codeasm-regions-{}.py", std::process::id()));
+        let path = path.to_str().unwrap();
+        let file = |default: Stmt| {
+            let body = Block::new().push(Stmt::region("validate", [default]));
+            let body = body.push(Stmt::ret(Some(Expr::raw("x"))));
+            let args = DefArgs::new().push_arg("x", Type::unknow(), None).unwrap();
+            File::new().push(Stmt::func("check", args, Type::unknow(), body))
+        };
+        file(Stmt::pass()).save(path).unwrap();
+        let saved = std::fs::read_to_string(path).unwrap();
+        assert!(saved.contains("    # codeasm:begin validate\n    pass\n    # codeasm:end\n"));
+
+        let edited = saved.replace("    pass\n", "    assert x > 0\n    x += 1\n");
+        std::fs::write(path, &edited).unwrap();
+        file(Stmt::raw("...")).save(path).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), edited);
+
+        let err = File::new().push(Stmt::pass()).save(path).unwrap_err();
+        let err = err.into_inner().unwrap().downcast::<RegionError>().unwrap();
+        assert_eq!(
+            *err,
+            RegionError::Missing { file: path.into(), region: "validate".into() }
+        );
+        // an unreadable file isn't taken for a missing one and overwritten.
+        std::fs::write(path, [0xff, 0xfe]).unwrap();
+        let err = file(Stmt::pass()).save(path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(std::fs::read(path).unwrap(), [0xff, 0xfe]);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn source_map() {
+        use super::*;
+        let user = "schema.json#/definitions/User";
+        let field = Stmt::raw("name: str").origin(format!("{user}/properties/name"));
+        let class = Stmt::class("User", Vec::<&str>::new(), Block::new().push(field)).origin(user);
+        let file = File::new().push(Stmt::import("dataclasses")).push(class.clone());
+        let (src, map) = file.source_map("models.py");
+        assert_eq!(src, "import dataclasses\nclass User:\n    name: str\n\n");
+        assert_eq!(src, file.to_string());
+        assert_eq!(class.to_string(), "class User:\n    name: str\n");
+        let block = Block::new().push(class.clone());
+        assert_eq!(block.to_string(), "    class User:\n        name: str\n");
+        assert!(file.validate().is_ok());
+        assert_eq!(map.origin(0, 0), None);
+        assert_eq!(map.origin(1, 3), Some(user));
+        assert_eq!(map.origin(2, 0), Some(user));
+        assert_eq!(map.origin(2, 6), Some("schema.json#/definitions/User/properties/name"));
+        assert_eq!(
+            map.to_json(),
+            r#"{"version":3,"file":"models.py","sources":["schema.json#/definitions/User","#
+                .to_string()
+                + r#""schema.json#/definitions/User/properties/name"],"names":[],"#
+                + r#""mappings":";AAAA;AAAA,ICAA;"}"#
+        );
+
+        let path = std::env::temp_dir().join(format!("codeasm-map-{}.py", std::process::id()));
+        let path = path.to_str().unwrap();
+        file.save_with_source_map(path).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), src);
+        let map_path = format!("{path}.map");
+        assert!(std::fs::read_to_string(&map_path).unwrap().contains("\"file\":\"codeasm-map-"));
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(map_path).unwrap();
+    }
+
+    #[test]
+    fn nested_strings() {
+        use super::*;
+        let doc = Stmt::docstring("Count.", [("for i in range(2):\n    print(i)", "0\n1")]);
+        let self_ = DefArgs::new().push_arg("self", Type::unknow(), None).unwrap();
+        let method = Block::new().push(doc.clone()).push(Stmt::pass());
+        let class = Block::new().push(Stmt::func("count", self_, Type::unknow(), method));
+        let file = File::new().push(Stmt::class("Counter", Vec::<&str>::new(), class));
+        let src = file.to_string();
+        let nested = "class Counter:\n    def count(self):\n        \"\"\"Count.\n\n\
+            >>> for i in range(2):\n...     print(i)\n0\n1\n\"\"\"\n        pass\n\n";
+        assert_eq!(src, nested);
+        // the docstring has the same value nested as at the top level.
+        let body = &src[src.find("\"\"\"").unwrap()..src.rfind("\"\"\"").unwrap() + 3];
+        assert_eq!(body, doc.0);
+        assert_eq!(file.validate(), Ok(()));
+    }
 }
@@ -0,0 +1,111 @@
+//! Turn arbitrary names into Python identifiers, following PEP 8 casing.
+
+use super::lexer::KEYWORDS;
+use crate::naming::{pascal_case, snake_case, upper_snake_case};
+
+/// Builtin functions and types, valid names that shadow the builtins.
+pub const BUILTINS: [&str; 70] = [
+    "abs",
+    "aiter",
+    "all",
+    "anext",
+    "any",
+    "ascii",
+    "bin",
+    "bool",
+    "breakpoint",
+    "bytearray",
+    "bytes",
+    "callable",
+    "chr",
+    "classmethod",
+    "compile",
+    "complex",
+    "delattr",
+    "dict",
+    "dir",
+    "divmod",
+    "enumerate",
+    "eval",
+    "exec",
+    "filter",
+    "float",
+    "format",
+    "frozenset",
+    "getattr",
+    "globals",
+    "hasattr",
+    "hash",
+    "help",
+    "hex",
+    "id",
+    "input",
+    "int",
+    "isinstance",
+    "issubclass",
+    "iter",
+    "len",
+    "list",
+    "locals",
+    "map",
+    "max",
+    "memoryview",
+    "min",
+    "next",
+    "object",
+    "oct",
+    "open",
+    "ord",
+    "pow",
+    "print",
+    "property",
+    "range",
+    "repr",
+    "reversed",
+    "round",
+    "set",
+    "setattr",
+    "slice",
+    "sorted",
+    "staticmethod",
+    "str",
+    "sum",
+    "super",
+    "tuple",
+    "type",
+    "vars",
+    "zip",
+];
+
+/// Variable, function or module name. e.g. `userId` -> `user_id`, `class` -> `class_`
+pub fn ident(name: &str) -> String {
+    escape(snake_case(name))
+}
+
+/// Class name. e.g. `http_server` -> `HttpServer`
+pub fn class_name(name: &str) -> String {
+    escape(pascal_case(name))
+}
+
+/// Constant name. e.g. `maxRetries` -> `MAX_RETRIES`
+pub fn constant(name: &str) -> String {
+    escape(upper_snake_case(name))
+}
+
+/// Whether `name` is a keyword or builtin that needs escaping.
+pub fn is_reserved(name: &str) -> bool {
+    KEYWORDS.contains(&name) || BUILTINS.contains(&name)
+}
+
+/// Keywords and builtins get a `_` suffix, `_` is prepended to a leading digit.
+fn escape(name: String) -> String {
+    if name.is_empty() {
+        "_".into()
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else if is_reserved(&name) {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
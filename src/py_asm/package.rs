@@ -0,0 +1,234 @@
+use super::lexer::KEYWORDS;
+use super::*;
+use std::collections::BTreeMap;
+use crate::output::{Header, SaveReport};
+use std::path::Path;
+
+/// A Python package: a directory of modules with `__init__.py` files, a `py.typed` marker and a
+/// minimal `pyproject.toml` next to it. e.g. for `acme_sdk` with the module `api.client`
+/// ```text
+/// pyproject.toml
+/// acme_sdk/__init__.py
+/// acme_sdk/py.typed
+/// acme_sdk/api/__init__.py
+/// acme_sdk/api/client.py
+/// ```
+#[derive(Clone)]
+pub struct Package {
+    /// Import name. e.g. `acme_sdk`
+    pub name: String,
+    pub version: String,
+    /// Oldest supported Python, the `requires-python` of `pyproject.toml`.
+    pub python: Version,
+    /// Requirement specifiers. e.g. `httpx>=0.27`
+    pub dependencies: Vec<String>,
+    /// Modules by dotted path relative to the package. e.g. `api.client`
+    pub modules: BTreeMap<String, File>,
+    /// Names re-exported from the top-level `__init__.py`, by module.
+    pub exports: BTreeMap<String, Vec<String>>,
+    /// Import between modules of the package with `from .. import` instead of the full path.
+    pub relative: bool,
+    /// Header of every `.py` file, see [`File::header`].
+    pub header: Option<Header>,
+}
+
+impl Package {
+    /// A package named `name`, which must be an identifier like the parts of a module path.
+    pub fn new(name: impl Display, version: impl Display, python: Version) -> Result<Self, Error> {
+        let name = name.to_string();
+        if !is_module_part(&name) {
+            return Err(Error::InvalidPackage(name));
+        }
+        Ok(Self {
+            name,
+            version: version.to_string(),
+            python,
+            dependencies: Vec::new(),
+            modules: BTreeMap::new(),
+            exports: BTreeMap::new(),
+            relative: false,
+            header: None,
+        })
+    }
+
+    /// Mark every `.py` file of the package as generated.
+    pub fn header(self, header: Header) -> Self {
+        Self { header: Some(header), ..self }
+    }
+
+    pub fn dependency(mut self, spec: impl Display) -> Self {
+        self.dependencies.push(spec.to_string());
+        self
+    }
+
+    /// Make [`Package::import`] use relative imports.
+    pub fn relative(self) -> Self {
+        Self { relative: true, ..self }
+    }
+
+    /// Add a module, every part of its dotted path must be an identifier. `__init__` modules are
+    /// generated.
+    pub fn module(mut self, path: impl Display, file: File) -> Result<Self, Error> {
+        let path = path.to_string();
+        if !path.split('.').all(is_module_part) {
+            return Err(Error::InvalidModule(path));
+        }
+        // `api.py` and `api/__init__.py` would both be the module `api`.
+        if self.packages().contains(&path) {
+            return Err(Error::ModuleIsPackage(path));
+        }
+        let mut parents = path.match_indices('.').map(|(i, _)| &path[..i]);
+        if let Some(parent) = parents.find(|p| self.modules.contains_key(*p)) {
+            return Err(Error::ModuleIsPackage(parent.to_string()));
+        }
+        self.modules.insert(path, file);
+        Ok(self)
+    }
+
+    /// Re-export `names` of the module `path`, added before, from the top-level `__init__.py`,
+    /// listed in its `__all__`.
+    pub fn export(
+        mut self,
+        path: impl Display,
+        names: impl IntoIterator<Item = impl Display>,
+    ) -> Result<Self, Error> {
+        let path = path.to_string();
+        if !self.modules.contains_key(&path) {
+            return Err(Error::UnknownModule(path));
+        }
+        let names = names.into_iter().map(|n| n.to_string());
+        self.exports.entry(path).or_default().extend(names);
+        Ok(self)
+    }
+
+    /// Import `names` of the module or subpackage `target` into the module `from`, both dotted
+    /// paths relative to the package. e.g. from `api.client`, for `models` and `User`,
+    /// `from acme_sdk.models import User` or `from ..models import User` if relative.
+    pub fn import(
+        &self,
+        from: &str,
+        target: &str,
+        names: impl IntoIterator<Item = impl Display>,
+    ) -> Stmt {
+        let names = names.into_iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+        let target: Vec<_> = target.split('.').filter(|p| !p.is_empty()).collect();
+        if !self.relative {
+            let module = [self.name.as_str()].into_iter().chain(target).collect::<Vec<_>>();
+            return Stmt::from_import(module.join("."), names);
+        }
+        // the package containing `from`, the module itself is the last part.
+        let mut parent: Vec<_> = from.split('.').collect();
+        parent.pop();
+        let common = parent.iter().zip(&target).take_while(|(a, b)| a == b).count();
+        let dots = ".".repeat(1 + parent.len() - common);
+        Stmt::from_import(format!("{dots}{}", target[common..].join(".")), names)
+    }
+
+    /// Subpackages, every module's parent directory, the top-level package as `""`.
+    fn packages(&self) -> Vec<String> {
+        let mut res = vec![String::new()];
+        for path in self.modules.keys() {
+            let parts: Vec<_> = path.split('.').collect();
+            for i in 1..parts.len() {
+                let pkg = parts[..i].join(".");
+                if !res.contains(&pkg) {
+                    res.push(pkg);
+                }
+            }
+        }
+        res
+    }
+
+    fn init(&self) -> File {
+        let mut file = File::new();
+        let mut all = Vec::new();
+        for (path, names) in &self.exports {
+            file.pushs(self.import("__init__", path, names));
+            all.extend(names.iter().map(|n| Expr::str("", n)));
+        }
+        if !all.is_empty() {
+            file.pushs(Stmt::assign("__all__", Expr::list(all)));
+        }
+        file
+    }
+
+    /// `file` rendered with its header, or the one of the package.
+    fn file(&self, file: File) -> String {
+        let header = file.1.or_else(|| self.header.clone());
+        File(file.0, header).to_string()
+    }
+
+    pub fn pyproject(&self) -> String {
+        let Version(major, minor) = self.python;
+        let mut res = format!(
+            "[build-system]\nrequires = [\"setuptools>=61\"]\n\
+             build-backend = \"setuptools.build_meta\"\n\n\
+             [project]\nname = \"{}\"\nversion = \"{}\"\n\
+             requires-python = \">={major}.{minor}\"\n",
+            self.name.replace('_', "-"),
+            toml_escape(&self.version),
+        );
+        if self.dependencies.is_empty() {
+            res.push_str("dependencies = []\n");
+        } else {
+            res.push_str("dependencies = [\n");
+            for dep in &self.dependencies {
+                writeln!(res, "    \"{}\",", toml_escape(dep)).unwrap();
+            }
+            res.push_str("]\n");
+        }
+        let name = &self.name;
+        write!(res, "\n[tool.setuptools.package-data]\n{name} = [\"py.typed\"]\n").unwrap();
+        res
+    }
+
+    /// Contents of every file by path relative to the output directory, `pyproject.toml`
+    /// included. This is what [`Package::save`] writes, for a dry run.
+    pub fn render(&self) -> BTreeMap<String, String> {
+        let root = &self.name;
+        let mut res = BTreeMap::from([
+            ("pyproject.toml".to_string(), self.pyproject()),
+            (format!("{root}/py.typed"), String::new()),
+        ]);
+        for pkg in self.packages() {
+            let dir = [root.as_str()].into_iter().chain(pkg.split('.').filter(|p| !p.is_empty()));
+            let init = if pkg.is_empty() { self.init() } else { File::new() };
+            let path = format!("{}/__init__.py", dir.collect::<Vec<_>>().join("/"));
+            res.insert(path, self.file(init));
+        }
+        for (path, file) in &self.modules {
+            res.insert(format!("{root}/{}.py", path.replace('.', "/")), self.file(file.clone()));
+        }
+        res
+    }
+
+    /// Write the package to `dir`, replacing it if it exists. Files go to a sibling temporary
+    /// directory first, which is then renamed to `dir`, so a failed write leaves `dir` untouched.
+    /// Hand-written regions of the existing files are kept, see [`Stmt::region`], but every
+    /// other file of `dir`, e.g. `.git` or hand-written ones, is deleted. A non-empty `dir`
+    /// without a manifest of an earlier save is refused, use [`Self::sync`] to write into it.
+    /// Every file is rewritten, [`Self::sync`] writes only those that changed.
+    pub fn save(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        crate::output::write_tree(dir.as_ref(), self.render(), "#")
+    }
+
+    /// Write only the files whose content changed to `dir`, so that tools watching it don't
+    /// rebuild everything. With `prune`, files generated by an earlier save or sync that are no
+    /// longer produced are deleted.
+    pub fn sync(&self, dir: impl AsRef<Path>, prune: bool) -> std::io::Result<SaveReport> {
+        crate::output::sync_tree(dir.as_ref(), self.render(), "#", prune)
+    }
+}
+
+/// Whether `part` is an identifier usable as a package or module name, not `__init__`.
+fn is_module_part(part: &str) -> bool {
+    part.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && part != "__init__"
+        && !KEYWORDS.contains(&part)
+}
+
+/// Escape `s` for a TOML basic string.
+fn toml_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
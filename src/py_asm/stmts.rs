@@ -8,20 +8,35 @@ impl Block {
         Self::default()
     }
 
-    pub fn push(self, stmt: Stmt) -> Self {
-        Self(format!("{self}    {stmt}\n"))
+    pub fn push(mut self, stmt: Stmt) -> Self {
+        self.pushs(stmt);
+        self
     }
 
     /// push stmt to `&mut Block`
     pub fn pushs(&mut self, stmt: Stmt) -> &mut Self {
-        writeln!(self.0, "    {stmt}").unwrap();
+        self.0.push_str(&indent(&stmt.0));
         self
     }
 }
 
+/// Indent every line by one level, nested blocks of compound statements included. Lines inside
+/// multi-line strings are left as they are, indenting them would change the string.
+fn indent(code: &str) -> String {
+    let code = code.trim_end_matches('\n');
+    let in_string = super::lexer::string_lines(code);
+    code.lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let keep = line.is_empty() || in_string.contains(&(i + 1));
+            if keep { format!("{line}\n") } else { format!("    {line}\n") }
+        })
+        .collect()
+}
+
 impl Display for Block {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        crate::output::fmt_node(f, &self.0)
     }
 }
 
@@ -51,13 +66,30 @@ impl Stmt {
         Self(format!("del {expr}"))
     }
 
-    pub fn ret(val: Expr) -> Self {
-        Self(format!("return {val}"))
+    /// Record that the statement is produced from `origin`, e.g. `schema.json#/definitions/User`,
+    /// for [`File::source_map`].
+    pub fn origin(self, origin: impl Display) -> Self {
+        Self(crate::output::with_origin(origin, &self.0))
+    }
+
+    /// A region whose statements are hand-written, kept when saving over an existing file.
+    /// `stmts` are the default content, an empty region can't be all of a body. e.g.
+    /// ```python
+    /// # codeasm:begin validate
+    /// # codeasm:end
+    /// ```
+    pub fn region(name: impl Display, stmts: impl IntoIterator<Item = Stmt>) -> Self {
+        let body: Vec<_> = stmts.into_iter().map(|s| s.0).collect();
+        Self(crate::output::region("#", name, &body.join("\n")))
+    }
+
+    pub fn ret(val: Option<Expr>) -> Self {
+        Self(val.map_or("return".into(), |v| format!("return {v}")))
     }
 
     pub fn assert(cond: Expr, info: Option<Expr>) -> Self {
         let info = info.map_or(String::new(), |i| format!(", {i}"));
-        Self(format!("assert {cond}, {info}"))
+        Self(format!("assert {cond}{info}"))
     }
 
     pub fn raise(err: Expr) -> Self {
@@ -68,13 +100,38 @@ impl Stmt {
         Self(format!("yield {}", expr.map_or(String::new(), |e| e.to_string())))
     }
 
+    /// e.g. `yield from gen()`
+    pub fn yield_from(expr: Expr) -> Self {
+        Self(format!("yield from {expr}"))
+    }
+
     pub fn try_(
         body: Block,
         excepts: impl IntoIterator<Item = (Type, impl ToString, Block)>,
         else_: Block,
         finally: Block,
     ) -> Self {
-        let mut res = format!("try:\n{body}");
+        Self::try_impl("except", body, excepts, else_, finally)
+    }
+
+    /// `try` with `except*` clauses handling exception groups.
+    pub fn try_star(
+        body: Block,
+        excepts: impl IntoIterator<Item = (Type, impl ToString, Block)>,
+        else_: Block,
+        finally: Block,
+    ) -> Self {
+        Self::try_impl("except*", body, excepts, else_, finally)
+    }
+
+    fn try_impl(
+        except: &str,
+        body: Block,
+        excepts: impl IntoIterator<Item = (Type, impl ToString, Block)>,
+        else_: Block,
+        finally: Block,
+    ) -> Self {
+        let mut res = format!("try:\n{body:#}");
         for (exc_ty, exc_name, body) in excepts {
             let exc_name = exc_name.to_string();
             let exc = if exc_name.is_empty() {
@@ -82,13 +139,13 @@ impl Stmt {
             } else {
                 format!("{exc_ty} as {exc_name}")
             };
-            write!(res, "except {exc}:\n{body}").unwrap()
+            write!(res, "{except} {exc}:\n{body:#}").unwrap()
         }
         if !else_.0.is_empty() {
-            write!(res, "else:\n{else_}").unwrap();
+            write!(res, "else:\n{else_:#}").unwrap();
         }
         if !finally.0.is_empty() {
-            write!(res, "finally:\n{finally}").unwrap();
+            write!(res, "finally:\n{finally:#}").unwrap();
         }
         Self(res)
     }
@@ -99,34 +156,68 @@ impl Stmt {
             var = format!(" as {var}")
         }
         if else_.0.is_empty() {
-            Self(format!("with {expr}{var}:\n{body}"))
+            Self(format!("with {expr}{var}:\n{body:#}"))
         } else {
-            Self(format!("with {expr}{var}:\n{body}else:\n{else_}"))
+            Self(format!("with {expr}{var}:\n{body:#}else:\n{else_:#}"))
         }
     }
     pub fn async_with(expr: Expr, var: impl ToString, body: Block, else_: Block) -> Self {
-        Self(format!("async {}", Self::with(expr, var, body, else_)))
+        Self(format!("async {:#}", Self::with(expr, var, body, else_)))
+    }
+
+    /// `with` on several context managers. e.g. `with open(a) as f, lock:`
+    pub fn with_many(items: impl IntoIterator<Item = (Expr, impl ToString)>, body: Block) -> Self {
+        let items = items
+            .into_iter()
+            .map(|(expr, var)| {
+                let var = var.to_string();
+                if var.is_empty() { expr.to_string() } else { format!("{expr} as {var}") }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Self(format!("with {items}:\n{body:#}"))
+    }
+
+    pub fn async_with_many(
+        items: impl IntoIterator<Item = (Expr, impl ToString)>,
+        body: Block,
+    ) -> Self {
+        Self(format!("async {:#}", Self::with_many(items, body)))
+    }
+
+    /// `cases` are a pattern, an optional guard and a body.
+    /// e.g. `match p:\n    case Point(x=0) if y > 0:\n        ...`
+    pub fn match_(
+        subject: Expr,
+        cases: impl IntoIterator<Item = (Pattern, Option<Expr>, Block)>,
+    ) -> Self {
+        let mut res = format!("match {subject}:\n");
+        for (pattern, guard, body) in cases {
+            let guard = guard.map_or(String::new(), |g| format!(" if {g}"));
+            res.push_str(&indent(&format!("case {pattern}{guard}:\n{body:#}")));
+        }
+        Self(res)
     }
 
     pub fn if_(cases: impl IntoIterator<Item = (Expr, Block)>, else_: Block) -> Self {
         let mut res = String::new();
         let mut cases = cases.into_iter();
         let (cond, block) = cases.next().expect("`cases` is empty");
-        write!(res, "if {cond}:\n{block}").unwrap();
+        write!(res, "if {cond}:\n{block:#}").unwrap();
         for (cond, block) in cases {
-            write!(res, "elif {cond}:\n{block}").unwrap();
+            write!(res, "elif {cond}:\n{block:#}").unwrap();
         }
         if !else_.0.is_empty() {
-            write!(res, "else:\n{else_}").unwrap();
+            write!(res, "else:\n{else_:#}").unwrap();
         }
         Self(res)
     }
 
     pub fn while_(cond: Expr, body: Block, else_: Block) -> Self {
         if else_.0.is_empty() {
-            Self(format!("while {cond}:\n{body}"))
+            Self(format!("while {cond}:\n{body:#}"))
         } else {
-            Self(format!("while {cond}:\n{body}else:\n{else_}"))
+            Self(format!("while {cond}:\n{body:#}else:\n{else_:#}"))
         }
     }
 
@@ -146,9 +237,9 @@ impl Stmt {
             }
         }
         if else_.0.is_empty() {
-            Self(format!("for {vars} in {iters}:\n{body}"))
+            Self(format!("for {vars} in {iters}:\n{body:#}"))
         } else {
-            Self(format!("for {vars} in {iters}:\n{body}else:\n{else_}"))
+            Self(format!("for {vars} in {iters}:\n{body:#}else:\n{else_:#}"))
         }
     }
     pub fn async_for(
@@ -156,18 +247,98 @@ impl Stmt {
         body: Block,
         else_: Block,
     ) -> Self {
-        Self(format!("async {}", Self::for_(var_iters, body, else_)))
+        Self(format!("async {:#}", Self::for_(var_iters, body, else_)))
     }
 }
 
-impl Into<Stmt> for Expr {
-    fn into(self) -> Stmt {
-        Stmt::raw(self)
+/// A pattern of a `case` clause in [`Stmt::match_`].
+#[derive(Clone)]
+pub struct Pattern(pub String);
+
+impl Pattern {
+    pub fn raw(raw: impl ToString) -> Self {
+        Self(raw.to_string())
+    }
+
+    /// `_`
+    pub fn wildcard() -> Self {
+        Self("_".into())
+    }
+
+    /// Bind the subject to a name. e.g. `x`
+    pub fn capture(name: impl Display) -> Self {
+        Self(name.to_string())
+    }
+
+    /// Literal or dotted value. e.g. `0`, `"a"`, `Color.RED`
+    pub fn value(val: Expr) -> Self {
+        Self(val.to_string())
+    }
+
+    /// Collect the remaining items of a sequence pattern. e.g. `*rest`
+    pub fn star(name: impl Display) -> Self {
+        Self(format!("*{name}"))
+    }
+
+    /// e.g. `[x, *rest]`
+    pub fn seq(pats: impl IntoIterator<Item = Pattern>) -> Self {
+        let pats = pats.into_iter().map(|p| p.0).collect::<Vec<_>>().join(", ");
+        Self(format!("[{pats}]"))
+    }
+
+    /// e.g. `{"id": id, **rest}`, `rest` may be empty.
+    pub fn mapping(items: impl IntoIterator<Item = (Expr, Pattern)>, rest: impl ToString) -> Self {
+        let mut items: Vec<_> = items.into_iter().map(|(k, v)| format!("{k}: {v}")).collect();
+        let rest = rest.to_string();
+        if !rest.is_empty() {
+            items.push(format!("**{rest}"));
+        }
+        Self(format!("{{{}}}", items.join(", ")))
+    }
+
+    /// e.g. `Point(x, y=0)`
+    pub fn class(
+        cls: Expr,
+        args: impl IntoIterator<Item = Pattern>,
+        kwargs: impl IntoIterator<Item = (impl Display, Pattern)>,
+    ) -> Self {
+        let args = args.into_iter().map(|a| a.0);
+        let kwargs = kwargs.into_iter().map(|(kw, p)| format!("{kw}={p}"));
+        Self(format!("{cls}({})", args.chain(kwargs).collect::<Vec<_>>().join(", ")))
+    }
+
+    /// e.g. `0 | 1`
+    pub fn or(pats: impl IntoIterator<Item = Pattern>) -> Self {
+        Self(pats.into_iter().map(|p| p.0).collect::<Vec<_>>().join(" | "))
+    }
+
+    /// e.g. `[x, y] as point`
+    pub fn bind(self, name: impl Display) -> Self {
+        Self(format!("{self} as {name}"))
     }
 }
 
-impl Display for Stmt {
+impl Display for Pattern {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
     }
 }
+
+impl From<Expr> for Stmt {
+    fn from(value: Expr) -> Self {
+        Stmt::raw(value)
+    }
+}
+
+/// Assignment and loop targets, imports, `with`/`except` aliases and definitions.
+impl Bindings for Stmt {
+    fn bindings(&self) -> Vec<String> {
+        super::validate::bindings(&self.0)
+    }
+}
+
+impl Display for Stmt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        crate::output::fmt_node(f, &self.0)
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+
+/// pytest tests, the file must `import pytest` for fixtures and `parametrize`.
+impl Stmt {
+    /// e.g. `Stmt::test("parse", args, body)` -> `def test_parse(tmp_path):`, the arguments are
+    /// fixtures or parametrized names.
+    pub fn test(name: impl Display, args: DefArgs, body: Block) -> Self {
+        Self::func(format!("test_{name}"), args, Type::unknow(), body)
+    }
+
+    /// A fixture, `scope` is `function` if empty. Code after a `yield` in `body` is the teardown.
+    /// e.g. `@pytest.fixture(scope="module")` and `def client():`
+    pub fn fixture(name: impl Display, scope: &str, args: DefArgs, body: Block) -> Self {
+        let dec = Expr::raw("pytest.fixture");
+        let dec =
+            if scope.is_empty() { dec } else { dec.call([], [("scope", Expr::str("", scope))]) };
+        let func = Self::func(name, args, Type::unknow(), body);
+        Self(format!("{}\n{func:#}", Self::dec(dec)))
+    }
+
+    /// Decorator running a test once per case, a case has one value per name.
+    /// e.g. `@pytest.mark.parametrize("a, b", [(1, 2), (3, 4)])`
+    pub fn parametrize(
+        names: impl IntoIterator<Item = impl Display>,
+        cases: impl IntoIterator<Item = impl IntoIterator<Item = Expr>>,
+    ) -> Result<Self, Error> {
+        let names: Vec<_> = names.into_iter().map(|n| n.to_string()).collect();
+        let mut vals = Vec::new();
+        for case in cases {
+            let case: Vec<_> = case.into_iter().collect();
+            if case.len() != names.len() {
+                return Err(Error::ParametrizeCase(Expr::tuple(case).0));
+            }
+            vals.push(match <[Expr; 1]>::try_from(case) {
+                Ok([val]) => val,
+                Err(case) => Expr::tuple(case),
+            });
+        }
+        let names = Expr::str("", names.join(", "));
+        let dec = Expr::raw("pytest.mark.parametrize")
+            .call([names, Expr::list(vals)], Vec::<(&str, _)>::new());
+        Ok(Self::dec(dec))
+    }
+
+    /// A docstring with doctest examples of source and expected output, continuation lines of
+    /// the source get `... ` and blank output lines `<BLANKLINE>`. e.g.
+    /// ```python
+    /// """Add two numbers.
+    ///
+    /// >>> add(1, 2)
+    /// 3
+    /// """
+    /// ```
+    pub fn docstring(
+        summary: impl Display,
+        examples: impl IntoIterator<Item = (impl Display, impl Display)>,
+    ) -> Self {
+        let mut doc = summary.to_string();
+        for (i, (src, output)) in examples.into_iter().enumerate() {
+            doc.push_str(if i == 0 { "\n\n" } else { "\n" });
+            for (j, line) in src.to_string().lines().enumerate() {
+                let prompt = if j == 0 { ">>>" } else { "..." };
+                writeln!(doc, "{prompt} {line}").unwrap();
+            }
+            for line in output.to_string().lines() {
+                doc.push_str(if line.trim().is_empty() { "<BLANKLINE>" } else { line });
+                doc.push('\n');
+            }
+        }
+        let mut doc = doc.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+        // a last unescaped `"` would run into the closing quotes.
+        if let Some(rest) = doc.strip_suffix('"')
+            && rest.chars().rev().take_while(|c| *c == '\\').count() % 2 == 0
+        {
+            doc.insert(doc.len() - 1, '\\');
+        }
+        Self(format!("\"\"\"{doc}\"\"\""))
+    }
+}
+
+/// A `unittest.TestCase` subclass. e.g.
+/// ```python
+/// class TestParser(unittest.TestCase):
+///     def setUp(self):
+///         ...
+///     def test_parse(self):
+///         ...
+/// ```
+/// The file must `import unittest`.
+#[derive(Clone, Default)]
+pub struct TestCase {
+    pub name: String,
+    pub set_up: Block,
+    pub tear_down: Block,
+    /// Test method names and bodies.
+    pub tests: Vec<(String, Block)>,
+}
+
+impl TestCase {
+    pub fn new(name: impl Display) -> Self {
+        Self { name: name.to_string(), ..Default::default() }
+    }
+
+    /// Body of `setUp`, run before each test.
+    pub fn set_up(self, body: Block) -> Self {
+        Self { set_up: body, ..self }
+    }
+
+    /// Body of `tearDown`, run after each test.
+    pub fn tear_down(self, body: Block) -> Self {
+        Self { tear_down: body, ..self }
+    }
+
+    /// e.g. `test("parse", body)` -> `def test_parse(self):`
+    pub fn test(mut self, name: impl Display, body: Block) -> Self {
+        self.tests.push((format!("test_{name}"), body));
+        self
+    }
+}
+
+impl From<TestCase> for Stmt {
+    fn from(tc: TestCase) -> Self {
+        let hooks = [("setUp".to_string(), tc.set_up), ("tearDown".to_string(), tc.tear_down)];
+        let hooks = hooks.into_iter().filter(|(_, body)| !body.0.is_empty());
+        // a lone parameter, no ordering to check.
+        let args = DefArgs(vec![Arg::new("self", Type::unknow(), None, ArgKind::Normal)]);
+        let mut body = Block::new();
+        for (name, method) in hooks.chain(tc.tests) {
+            body = body.push(Stmt::func(name, args.clone(), Type::unknow(), method));
+        }
+        if body.0.is_empty() {
+            body = body.push(Stmt::pass());
+        }
+        Stmt::class(tc.name, ["unittest.TestCase"], body)
+    }
+}
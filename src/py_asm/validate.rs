@@ -0,0 +1,627 @@
+use super::lexer::{Kind, Line, Token, logical_lines};
+use super::*;
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Code that doesn't parse, usually from `raw` nodes.
+    Syntax,
+    /// A bound name that is a keyword, or a soft keyword statement in the wrong place.
+    /// e.g. `class = 1`, `case` outside `match`
+    KeywordIdent,
+    /// `return`, `yield`, `await`, `break` or `continue` where it isn't allowed.
+    Misplaced,
+    /// `nonlocal` without a binding in an enclosing function.
+    NonlocalBinding,
+    /// A compound statement without body, fixed by [`File::fill_empty_bodies`].
+    EmptyBody,
+    /// Parameters in the wrong order, see [`DefArgs`].
+    Args,
+}
+
+/// A problem found by [`File::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// Enclosing nodes, outermost first. e.g. `["class Foo", "def run", "for"]`
+    pub path: Vec<String>,
+    /// 1-based line in the rendered file.
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.line)?;
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path.join(" > "))?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl File {
+    /// Check the rendered file for mistakes that Python would reject at compile time: misplaced
+    /// `return`/`yield`/`await`/`break`/`continue`, `nonlocal` without binding, keyword
+    /// identifiers, empty bodies and parameter ordering.
+    pub fn validate(&self) -> Result<(), Vec<Diagnostic>> {
+        let mut v = Validator::new(logical_lines(&self.to_string()));
+        v.file();
+        if v.diags.is_empty() { Ok(()) } else { Err(v.diags) }
+    }
+
+    /// Insert `pass` into every empty body, which is a syntax error otherwise.
+    pub fn fill_empty_bodies(self) -> Self {
+        // an origin may wrap several lines, but its markers contain no newline, so line numbers
+        // are the same without them.
+        let lines = logical_lines(&crate::output::strip_origins(&self.0));
+        let mut empty = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let block = line.toks.last().is_some_and(|t| t.is(":"));
+            if block && lines.get(i + 1).is_none_or(|next| next.indent <= line.indent) {
+                empty.push((line.end_line, line.indent + 4));
+            }
+        }
+        if empty.is_empty() {
+            return self;
+        }
+        let mut res = String::new();
+        let mut empty = empty.into_iter().peekable();
+        for (i, text) in self.0.lines().enumerate() {
+            writeln!(res, "{text}").unwrap();
+            while let Some((_, indent)) = empty.next_if(|(end, _)| *end == i + 1) {
+                writeln!(res, "{:indent$}pass", "").unwrap();
+            }
+        }
+        Self(res, self.1)
+    }
+}
+
+/// Names bound at the top level of `src`, nested function and class bodies excluded.
+pub(crate) fn bindings(src: &str) -> Vec<String> {
+    let mut v = Validator::new(logical_lines(&crate::output::strip_origins(src)));
+    v.bindings_only = true;
+    v.suite(None, Frame { scope: 0, is_async: false, in_loop: false }, false);
+    let mut names: Vec<_> = v.scopes.swap_remove(0).bindings.into_iter().collect();
+    names.sort();
+    names
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ScopeKind {
+    Module,
+    Class,
+    Func,
+}
+
+struct Scope {
+    kind: ScopeKind,
+    parent: usize,
+    bindings: HashSet<String>,
+    /// Names declared `nonlocal`, with the diagnostic to report if no binding is found.
+    nonlocals: Vec<(String, Diagnostic)>,
+}
+
+#[derive(Clone, Copy)]
+struct Frame {
+    scope: usize,
+    is_async: bool,
+    in_loop: bool,
+}
+
+const COMPOUND: [&str; 11] =
+    ["if", "elif", "else", "for", "while", "try", "except", "finally", "with", "def", "class"];
+const AUG_OPS: [&str; 13] =
+    ["+=", "-=", "*=", "/=", "//=", "%=", "**=", ">>=", "<<=", "&=", "|=", "^=", "@="];
+
+struct Validator {
+    lines: Vec<Line>,
+    pos: usize,
+    diags: Vec<Diagnostic>,
+    path: Vec<String>,
+    scopes: Vec<Scope>,
+    /// Only collect the bindings of the top-level scope, for [`bindings`].
+    bindings_only: bool,
+}
+
+impl Validator {
+    fn new(lines: Vec<Line>) -> Self {
+        let module = Scope {
+            kind: ScopeKind::Module,
+            parent: 0,
+            bindings: HashSet::new(),
+            nonlocals: vec![],
+        };
+        let (diags, path) = (Vec::new(), Vec::new());
+        Self { lines, pos: 0, diags, path, scopes: vec![module], bindings_only: false }
+    }
+
+    fn diag(&mut self, kind: DiagnosticKind, line: usize, message: String) {
+        if self.bindings_only {
+            return;
+        }
+        self.diags.push(Diagnostic { kind, path: self.path.clone(), line, message });
+    }
+
+    fn file(&mut self) {
+        if let Some(first) = self.lines.first()
+            && first.indent > 0
+        {
+            let line = first.line;
+            self.diag(DiagnosticKind::Syntax, line, "unexpected indent".into());
+        }
+        let toks = self.lines.iter().flat_map(|l| &l.toks);
+        let unterminated: Vec<_> =
+            toks.filter(|t| t.kind == Kind::Unterminated).map(|t| t.line).collect();
+        for line in unterminated {
+            self.diag(DiagnosticKind::Syntax, line, "unterminated string literal".into());
+        }
+        let frame = Frame { scope: 0, is_async: false, in_loop: false };
+        self.suite(None, frame, false);
+        for i in 0..self.scopes.len() {
+            for (name, diag) in std::mem::take(&mut self.scopes[i].nonlocals) {
+                if !self.resolves(i, &name) {
+                    self.diags.push(diag);
+                }
+            }
+        }
+    }
+
+    /// Whether `name` is bound in a function enclosing scope `i`.
+    fn resolves(&self, mut i: usize, name: &str) -> bool {
+        while i != 0 {
+            i = self.scopes[i].parent;
+            let scope = &self.scopes[i];
+            if scope.kind == ScopeKind::Func && scope.bindings.contains(name) {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn new_scope(&mut self, kind: ScopeKind, parent: usize) -> usize {
+        self.scopes.push(Scope { kind, parent, bindings: HashSet::new(), nonlocals: vec![] });
+        self.scopes.len() - 1
+    }
+
+    /// Bind `tok` in `scope`, it must be an identifier.
+    fn bind(&mut self, scope: usize, tok: &Token) {
+        if tok.kind == Kind::Keyword {
+            let msg = format!("`{}` is a keyword and can't be used as an identifier", tok.text);
+            self.diag(DiagnosticKind::KeywordIdent, tok.line, msg);
+        } else if tok.kind == Kind::Name {
+            self.scopes[scope].bindings.insert(tok.text.clone());
+        } else {
+            let msg = format!("cannot assign to `{}`", tok.text);
+            self.diag(DiagnosticKind::Syntax, tok.line, msg);
+        }
+    }
+
+    /// Statements indented deeper than `parent`, or all of them at the top level.
+    fn suite(&mut self, parent: Option<usize>, frame: Frame, in_match: bool) {
+        let Some(body) = self.lines.get(self.pos).map(|l| l.indent) else { return };
+        let mut prev = None;
+        while let Some(line) = self.lines.get(self.pos) {
+            if parent.is_some_and(|p| line.indent <= p) {
+                break;
+            }
+            if line.indent != body {
+                let msg = if line.indent > body {
+                    "unexpected indent"
+                } else {
+                    "unindent does not match any outer indentation level"
+                };
+                let line = line.line;
+                self.diag(DiagnosticKind::Syntax, line, msg.into());
+            }
+            prev = self.line(frame, prev, in_match);
+        }
+    }
+
+    /// A logical line, returns the clause keyword of a compound statement.
+    fn line(
+        &mut self,
+        frame: Frame,
+        prev: Option<&'static str>,
+        in_match: bool,
+    ) -> Option<&'static str> {
+        let line = self.lines[self.pos].clone();
+        self.pos += 1;
+        let toks = &line.toks;
+        let Some((kw, skip)) = compound(toks) else {
+            if in_match {
+                let msg = "only `case` clauses are allowed in a `match` body".into();
+                self.diag(DiagnosticKind::KeywordIdent, line.line, msg);
+            }
+            for stmt in split(toks, ";") {
+                if !stmt.is_empty() {
+                    self.simple(stmt, frame);
+                }
+            }
+            return None;
+        };
+        // a keyword name is reported by `bind`, don't take it for a lambda.
+        let named = matches!(kw, "def" | "async def" | "class");
+        let Some(colon) = header_colon(toks, if named { skip + 1 } else { skip }) else {
+            self.diag(DiagnosticKind::Syntax, line.line, format!("expected `:` after `{kw}`"));
+            return None;
+        };
+        let (header, inline) = (&toks[skip..colon], &toks[colon + 1..]);
+        let allowed: &[&str] = match kw {
+            "elif" => &["if", "elif"],
+            "else" => &["if", "elif", "for", "while", "try", "except"],
+            "except" => &["try", "except"],
+            "finally" => &["try", "except", "try-else"],
+            _ => &[],
+        };
+        if !allowed.is_empty() && !prev.is_some_and(|p| allowed.contains(&p)) {
+            let msg = format!("`{kw}` without a matching statement");
+            self.diag(DiagnosticKind::Syntax, line.line, msg);
+        }
+        if in_match != (kw == "case") {
+            let msg = if in_match {
+                "only `case` clauses are allowed in a `match` body"
+            } else {
+                "`case` outside of `match`"
+            };
+            self.diag(DiagnosticKind::KeywordIdent, line.line, msg.into());
+        }
+        let is_async = kw.starts_with("async");
+        if is_async && kw != "async def" && !frame.is_async {
+            let msg = format!("`{kw}` outside async function");
+            self.diag(DiagnosticKind::Misplaced, line.line, msg);
+        }
+        let mut child = frame;
+        let label = match kw {
+            "def" | "async def" | "class" => {
+                let Some(name) = header.first() else {
+                    self.diag(DiagnosticKind::Syntax, line.line, format!("missing `{kw}` name"));
+                    return None;
+                };
+                self.bind(frame.scope, name);
+                if self.bindings_only {
+                    // the body is a scope of its own.
+                    while self.lines.get(self.pos).is_some_and(|l| l.indent > line.indent) {
+                        self.pos += 1;
+                    }
+                    return Some(kw);
+                }
+                if kw == "class" {
+                    child.scope = self.new_scope(ScopeKind::Class, frame.scope);
+                    self.expr(&header[1..], frame);
+                    child.is_async = false;
+                } else {
+                    child.scope = self.new_scope(ScopeKind::Func, frame.scope);
+                    self.path.push(format!("def {}", name.text));
+                    self.params(&header[1..], frame, child.scope);
+                    self.path.pop();
+                    child.is_async = is_async;
+                }
+                child.in_loop = false;
+                format!("{} {}", kw.trim_start_matches("async "), name.text)
+            }
+            "for" | "async for" => {
+                let in_at = header.iter().position(|t| t.is("in")).unwrap_or(header.len());
+                self.targets(&header[..in_at], frame);
+                self.expr(&header[in_at..], frame);
+                child.in_loop = true;
+                kw.into()
+            }
+            "while" => {
+                self.expr(header, frame);
+                child.in_loop = true;
+                kw.into()
+            }
+            _ => {
+                // `with a as b`, `except E as e`, case patterns.
+                self.expr(header, frame);
+                for (i, tok) in header.iter().enumerate() {
+                    if tok.is("as")
+                        && let Some(name) = header.get(i + 1)
+                    {
+                        self.bind(frame.scope, name);
+                    }
+                }
+                kw.into()
+            }
+        };
+        self.path.push(label);
+        if !inline.is_empty() {
+            for stmt in split(inline, ";") {
+                if !stmt.is_empty() {
+                    self.simple(stmt, child);
+                }
+            }
+        } else if self.lines.get(self.pos).is_some_and(|next| next.indent > line.indent) {
+            self.suite(Some(line.indent), child, kw == "match");
+        } else {
+            let msg = format!("expected an indented block after `{kw}`");
+            self.diag(DiagnosticKind::EmptyBody, line.line, msg);
+        }
+        self.path.pop();
+        Some(match kw {
+            "async for" => "for",
+            "else" if prev == Some("try") || prev == Some("except") => "try-else",
+            _ => kw,
+        })
+    }
+
+    /// Parameters of a `def` header, `toks` starts at the opening parenthesis.
+    fn params(&mut self, mut toks: &[Token], frame: Frame, scope: usize) {
+        // type parameters. e.g. `def first[T](xs: list[T]) -> T`
+        if toks.first().is_some_and(|t| t.is("[")) {
+            toks = &toks[matching(toks, 0) + 1..];
+        }
+        let Some(close) = toks.first().filter(|t| t.is("(")).map(|_| matching(toks, 0)) else {
+            let line = toks.first().map_or(0, |t| t.line);
+            self.diag(DiagnosticKind::Syntax, line, "expected `(`".into());
+            return;
+        };
+        self.expr(&toks[close + 1..], frame);
+        let elems = split(&toks[1..close], ",");
+        let elems: Vec<_> = elems.into_iter().filter(|e| !e.is_empty()).collect();
+        let slash = elems.iter().position(|e| e.len() == 1 && e[0].is("/"));
+        let mut args = DefArgs::new();
+        let (mut starred, mut bare_star) = (false, None);
+        for (i, elem) in elems.iter().enumerate() {
+            let line = elem[0].line;
+            let (kind, name) = match elem[0].text.as_str() {
+                "/" if elem.len() == 1 => {
+                    if starred || slash != Some(i) || i == 0 {
+                        self.diag(DiagnosticKind::Args, line, "`/` is out of order".into());
+                    }
+                    continue;
+                }
+                "*" if elem.len() == 1 => {
+                    if starred {
+                        self.diag(DiagnosticKind::Args, line, "`*` may appear only once".into());
+                    }
+                    (starred, bare_star) = (true, Some(line));
+                    continue;
+                }
+                "*" => (ArgKind::VarArgs, &elem[1]),
+                "**" if elem.len() > 1 => (ArgKind::KwArgs, &elem[1]),
+                _ if slash.is_some_and(|s| i < s) => (ArgKind::PosOnly, &elem[0]),
+                _ if starred => (ArgKind::KwOnly, &elem[0]),
+                _ => (ArgKind::Normal, &elem[0]),
+            };
+            if kind == ArgKind::VarArgs {
+                starred = true;
+            }
+            if kind == ArgKind::KwOnly {
+                bare_star = None;
+            }
+            let eq = elem.iter().position(|t| t.is("="));
+            if let Some(eq) = eq {
+                self.expr(&elem[eq + 1..], frame);
+            }
+            self.bind(scope, name);
+            let default = eq.map(|_| Expr::raw("..."));
+            match args.clone().push(Arg::new(&name.text, Type::unknow(), default, kind)) {
+                Ok(pushed) => args = pushed,
+                Err(e) => self.diag(DiagnosticKind::Args, line, e.to_string()),
+            }
+        }
+        if let Some(line) = bare_star {
+            let msg = "named arguments must follow bare `*`".into();
+            self.diag(DiagnosticKind::Args, line, msg);
+        }
+    }
+
+    fn simple(&mut self, toks: &[Token], frame: Frame) {
+        let first = &toks[0];
+        let line = first.line;
+        let kind = self.scopes[frame.scope].kind;
+        match first.text.as_str() {
+            "return" if first.kind == Kind::Keyword && kind != ScopeKind::Func => {
+                self.diag(DiagnosticKind::Misplaced, line, "`return` outside function".into());
+            }
+            "break" if first.kind == Kind::Keyword && !frame.in_loop => {
+                self.diag(DiagnosticKind::Misplaced, line, "`break` outside loop".into());
+            }
+            "continue" if first.kind == Kind::Keyword && !frame.in_loop => {
+                let msg = "`continue` not properly in loop".into();
+                self.diag(DiagnosticKind::Misplaced, line, msg);
+            }
+            "nonlocal" | "global" if first.kind == Kind::Keyword => {
+                for name in toks[1..].iter().filter(|t| !t.is(",")) {
+                    if first.text == "global" {
+                        continue;
+                    }
+                    if kind == ScopeKind::Module {
+                        let msg = "nonlocal declaration not allowed at module level".into();
+                        self.diag(DiagnosticKind::NonlocalBinding, line, msg);
+                        break;
+                    }
+                    let diag = Diagnostic {
+                        kind: DiagnosticKind::NonlocalBinding,
+                        path: self.path.clone(),
+                        line,
+                        message: format!("no binding for nonlocal `{}` found", name.text),
+                    };
+                    self.scopes[frame.scope].nonlocals.push((name.text.clone(), diag));
+                    self.bind(frame.scope, name);
+                }
+                return;
+            }
+            "import" | "from" if first.kind == Kind::Keyword => {
+                let start = toks.iter().position(|t| t.is("import")).map_or(toks.len(), |i| i + 1);
+                for elem in split(&toks[start..], ",") {
+                    let elem: Vec<_> = elem.iter().filter(|t| !t.is("(") && !t.is(")")).collect();
+                    match elem.as_slice() {
+                        [.., as_, name] if as_.is("as") => self.bind(frame.scope, name),
+                        [name, ..] if name.kind == Kind::Name => self.bind(frame.scope, name),
+                        _ => {}
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+        // assignment targets come before any top level `lambda`.
+        let lambda = toks.iter().position(|t| t.is("lambda")).unwrap_or(toks.len());
+        let parts = split(&toks[..lambda], "=");
+        if toks.len() > 1 && toks[1].is(":") {
+            self.targets(&toks[..1], frame);
+            self.expr(&toks[2..], frame);
+        } else if let [targets @ .., value] = parts.as_slice()
+            && !targets.is_empty()
+        {
+            for target in targets {
+                self.targets(target, frame);
+            }
+            let value = lambda - value.len();
+            self.expr(&toks[value..], frame);
+        } else if let Some(op) = toks.iter().position(|t| AUG_OPS.contains(&t.text.as_str())) {
+            self.targets(&toks[..op], frame);
+            self.expr(&toks[op + 1..], frame);
+        } else {
+            self.expr(toks, frame);
+        }
+    }
+
+    /// Bind the names assigned by a target list. e.g. `a, (b, *c)`, but not `a.b` or `a[i]`
+    fn targets(&mut self, toks: &[Token], frame: Frame) {
+        let mut i = 0;
+        while i < toks.len() {
+            let tok = &toks[i];
+            let next = toks.get(i + 1);
+            let prev_dot = i > 0 && toks[i - 1].is(".");
+            match tok.kind {
+                Kind::Op if ["(", "[", "{"].contains(&tok.text.as_str()) => {
+                    let subscript = i > 0
+                        && (matches!(toks[i - 1].kind, Kind::Name | Kind::Str)
+                            || toks[i - 1].is(")")
+                            || toks[i - 1].is("]"));
+                    if subscript {
+                        let end = matching(toks, i);
+                        self.expr(&toks[i + 1..end], frame);
+                        i = end;
+                    }
+                }
+                _ if prev_dot => {}
+                Kind::Name | Kind::Keyword => {
+                    if next.is_some_and(|n| n.is(".") || n.is("(") || n.is("[")) {
+                        self.expr(std::slice::from_ref(tok), frame);
+                    } else {
+                        self.bind(frame.scope, tok);
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Check `await`, `yield` and walrus targets in an expression.
+    fn expr(&mut self, toks: &[Token], frame: Frame) {
+        let kind = self.scopes[frame.scope].kind;
+        for (i, tok) in toks.iter().enumerate() {
+            if tok.kind == Kind::Keyword && (tok.text == "yield" || tok.text == "await") {
+                let msg = match (tok.text.as_str(), kind) {
+                    (_, ScopeKind::Module | ScopeKind::Class) => {
+                        format!("`{}` outside function", tok.text)
+                    }
+                    ("await", _) if !frame.is_async => "`await` outside async function".into(),
+                    _ => continue,
+                };
+                self.diag(DiagnosticKind::Misplaced, tok.line, msg);
+            } else if tok.is(":=")
+                && let Some(target) = i.checked_sub(1).map(|i| &toks[i])
+            {
+                self.bind(frame.scope, target);
+            }
+        }
+    }
+}
+
+/// Clause keyword of a compound statement and the number of tokens it spans.
+fn compound(toks: &[Token]) -> Option<(&'static str, usize)> {
+    let first = toks.first()?;
+    if first.kind == Kind::Keyword {
+        // assigning to a keyword, reported as a target. e.g. `class = 1`
+        if toks.get(1).is_some_and(|t| t.is("=")) {
+            return None;
+        }
+        if let Some(kw) = COMPOUND.iter().find(|kw| first.text == **kw) {
+            return Some((kw, 1));
+        }
+        let next = toks.get(1)?;
+        return match (first.text.as_str(), next.text.as_str()) {
+            ("async", "def") => Some(("async def", 2)),
+            ("async", "for") => Some(("async for", 2)),
+            ("async", "with") => Some(("async with", 2)),
+            _ => None,
+        };
+    }
+    // soft keywords, unless used as a name. e.g. `match = 1`, `case: int`
+    let soft = first.kind == Kind::Name && (first.text == "match" || first.text == "case");
+    let next = toks.get(1)?;
+    let name_use =
+        next.kind == Kind::Op && [":", "=", ".", ",", ")", "]"].contains(&next.text.as_str());
+    if soft && !name_use && toks.last()?.is(":") {
+        return Some((if first.text == "match" { "match" } else { "case" }, 1));
+    }
+    None
+}
+
+/// Index of the `:` ending a compound statement header.
+fn header_colon(toks: &[Token], from: usize) -> Option<usize> {
+    let (mut depth, mut lambdas) = (0usize, 0);
+    for (i, tok) in toks.iter().enumerate().skip(from) {
+        if tok.kind != Kind::Op && !tok.is("lambda") {
+            continue;
+        }
+        match tok.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            "lambda" if depth == 0 => lambdas += 1,
+            ":" if depth == 0 && lambdas > 0 => lambdas -= 1,
+            ":" if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the bracket closing the one at `i`.
+fn matching(toks: &[Token], i: usize) -> usize {
+    let mut depth = 0;
+    for (j, tok) in toks.iter().enumerate().skip(i) {
+        if tok.kind != Kind::Op {
+            continue;
+        }
+        match tok.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return j;
+                }
+            }
+            _ => {}
+        }
+    }
+    toks.len() - 1
+}
+
+/// Split on top level `sep`.
+fn split<'a>(toks: &'a [Token], sep: &str) -> Vec<&'a [Token]> {
+    let (mut res, mut start, mut depth) = (Vec::new(), 0, 0usize);
+    for (i, tok) in toks.iter().enumerate() {
+        if tok.kind != Kind::Op {
+            continue;
+        }
+        match tok.text.as_str() {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" => depth = depth.saturating_sub(1),
+            text if text == sep && depth == 0 => {
+                res.push(&toks[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    res.push(&toks[start..]);
+    res
+}